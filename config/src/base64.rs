@@ -0,0 +1,111 @@
+//! This module contains the decoding of values that contain binary data encoded as base64.
+
+use crate::{ConfigError, ConfigOption, ResolvedValues};
+
+impl ResolvedValues {
+    /// Decodes the value of an option from base64 (standard alphabet with padding, as
+    /// produced by `base64` on the command line)
+    ///
+    /// Returns an empty vector if the option has no value, for `list` options this is the
+    /// first value.
+    pub fn get_bytes_b64(&self, option: &ConfigOption) -> Result<Vec<u8>, ConfigError> {
+        let value = match self.first_value(option) {
+            Some(value) => value,
+            None => return Ok(vec![]),
+        };
+        decode(value).ok_or_else(|| ConfigError::InvalidBase64 {
+            name: option.name.to_string(),
+        })
+    }
+}
+
+// Checks whether the value is valid base64, used as clap validator for `base64` options
+pub(crate) fn validate(value: &str) -> Result<(), String> {
+    match decode(value) {
+        Some(_) => Ok(()),
+        None => Err(String::from("the value is not valid base64")),
+    }
+}
+
+// Decodes standard base64 with padding, returns None if the input is malformed
+fn decode(value: &str) -> Option<Vec<u8>> {
+    let bytes = value.as_bytes();
+    if !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut decoded = Vec::with_capacity(bytes.len() / 4 * 3);
+    for (index, chunk) in bytes.chunks(4).enumerate() {
+        let last = index == bytes.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|byte| **byte == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+
+        let mut group = 0u32;
+        for byte in &chunk[..4 - padding] {
+            group = group << 6 | u32::from(sextet(*byte)?);
+        }
+        group <<= 6 * padding as u32;
+
+        let group = group.to_be_bytes();
+        decoded.extend_from_slice(&group[1..4 - padding]);
+    }
+    Some(decoded)
+}
+
+fn sextet(byte: u8) -> Option<u8> {
+    match byte {
+        b'A'..=b'Z' => Some(byte - b'A'),
+        b'a'..=b'z' => Some(byte - b'a' + 26),
+        b'0'..=b'9' => Some(byte - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+    use crate::{ConfigError, ConfigOption, ResolvedValues};
+
+    #[test]
+    fn decode_valid() {
+        assert_eq!(decode(""), Some(vec![]));
+        assert_eq!(decode("Zg=="), Some(b"f".to_vec()));
+        assert_eq!(decode("Zm8="), Some(b"fo".to_vec()));
+        assert_eq!(decode("Zm9v"), Some(b"foo".to_vec()));
+        assert_eq!(decode("Zm9vYmFy"), Some(b"foobar".to_vec()));
+        assert_eq!(decode("AP8+/w=="), Some(vec![0x00, 0xFF, 0x3E, 0xFF]));
+    }
+
+    #[test]
+    fn decode_malformed() {
+        assert_eq!(decode("Zm9"), None);
+        assert_eq!(decode("Zm9v!A=="), None);
+        assert_eq!(decode("Zg==Zm9v"), None);
+        assert_eq!(decode("Z==="), None);
+    }
+
+    #[test]
+    fn get_bytes() {
+        const TOKEN: ConfigOption = ConfigOption {
+            name: "token",
+            takes_argument: true,
+            base64: true,
+            ..ConfigOption::DEFAULT
+        };
+
+        let values = ResolvedValues::with_value(TOKEN, "3q2+7w==");
+        assert_eq!(
+            values.get_bytes_b64(&TOKEN).expect("Error decoding value"),
+            vec![0xDE, 0xAD, 0xBE, 0xEF]
+        );
+
+        assert!(matches!(
+            ResolvedValues::with_value(TOKEN, "3q2+7w").get_bytes_b64(&TOKEN),
+            Err(ConfigError::InvalidBase64 { name }) if name == "token"
+        ));
+    }
+}
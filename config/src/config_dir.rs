@@ -0,0 +1,95 @@
+//! This module reads option values from a directory that contains one file per option, which
+//! is how Kubernetes mounts ConfigMaps and Secrets as volumes.
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{error, trace};
+
+/// Return the arguments for all files in the directory named by the environment variable
+///
+/// Every file becomes `--<filename>=<content>`, with surrounding whitespace removed from the
+/// content. Hidden entries (like the `..data` links Kubernetes creates) and nested directories
+/// are ignored. Errors are logged and the affected files skipped, just like for the config file.
+///
+/// * `environment` - The name of the environment variable that contains the directory
+pub fn args(environment: &str) -> Vec<OsString> {
+    let dir = match env::var_os(environment) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => return vec![],
+    };
+    let args = match read_dir(&dir) {
+        Ok(args) => args,
+        Err(err) => {
+            error!("{}: {}", dir.display(), err);
+            return vec![];
+        }
+    };
+    trace!(
+        "{}: arguments loaded from config directory: {:?}",
+        dir.display(),
+        args
+    );
+    args
+}
+
+fn read_dir(dir: &Path) -> std::io::Result<Vec<OsString>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = match entry.file_name().into_string() {
+            Ok(name) if !name.starts_with('.') => name,
+            Ok(_) => continue,
+            Err(name) => {
+                error!("{}: file name {:?} is not valid UTF-8", dir.display(), name);
+                continue;
+            }
+        };
+        // Follows symlinks, as the files in mounted volumes are links to the actual data
+        if entry.path().is_file() {
+            files.push((name, entry.path()));
+        }
+    }
+    // The order of directory entries is not defined, this keeps the arguments stable
+    files.sort();
+
+    let mut args = vec![];
+    for (name, path) in files {
+        match fs::read_to_string(&path) {
+            Ok(content) => args.push(OsString::from(format!("--{}={}", name, content.trim()))),
+            Err(err) => error!("{}: {}", path.display(), err),
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_dir;
+    use std::env;
+    use std::ffi::OsString;
+    use std::fs;
+
+    #[test]
+    fn mounted_directory() {
+        let dir = env::temp_dir().join("stackable-config-dir-mounted");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).expect("Error creating test directory");
+        fs::create_dir_all(dir.join("..data")).expect("Error creating test directory");
+        fs::write(dir.join("user"), "admin\n").expect("Error writing test file");
+        fs::write(dir.join("port"), "  8080 ").expect("Error writing test file");
+        fs::write(dir.join(".hidden"), "x").expect("Error writing test file");
+        fs::write(dir.join("nested").join("ignored"), "x").expect("Error writing test file");
+
+        assert_eq!(
+            read_dir(&dir).expect("Error reading directory"),
+            vec![
+                OsString::from("--port=8080"),
+                OsString::from("--user=admin")
+            ]
+        );
+        fs::remove_dir_all(dir).expect("Error removing test directory");
+    }
+}
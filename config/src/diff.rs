@@ -0,0 +1,140 @@
+//! This module compares two configurations, e.g. two versions of the same tool, to find the
+//! options that were added, removed or changed in between.
+
+use std::collections::HashMap;
+
+use crate::{ConfigOption, Configuration};
+
+/// The differences between two configurations as returned by `Configuration::diff`
+///
+/// All lists are sorted by the name of the option.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConfigDiff {
+    /// Options that only exist in the new configuration
+    pub added: Vec<ConfigOption>,
+    /// Options that only exist in the old configuration
+    pub removed: Vec<ConfigOption>,
+    /// Options that exist in both configurations but differ in any field other than the
+    /// name, as pairs of the old and the new definition
+    pub changed: Vec<(ConfigOption, ConfigOption)>,
+}
+
+impl ConfigDiff {
+    /// Whether both configurations define exactly the same options
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Configuration {
+    /// Compares the options of this (old) configuration with those of another (new) one
+    ///
+    /// Only the options are compared, not the name, version or about text of the
+    /// configurations.
+    pub fn diff(&self, other: &Configuration) -> ConfigDiff {
+        let new: HashMap<&str, &ConfigOption> = other
+            .options
+            .iter()
+            .map(|option| (option.name, option))
+            .collect();
+
+        let mut diff = ConfigDiff::default();
+        for old_option in self.options.iter() {
+            match new.get(old_option.name) {
+                None => diff.removed.push(old_option.clone()),
+                Some(new_option) if !same_definition(old_option, new_option) => diff
+                    .changed
+                    .push((old_option.clone(), (*new_option).clone())),
+                Some(_) => {}
+            }
+        }
+        diff.added = other
+            .options
+            .iter()
+            .filter(|option| !self.options.contains(*option))
+            .cloned()
+            .collect();
+
+        diff.added.sort();
+        diff.removed.sort();
+        diff.changed.sort();
+        diff
+    }
+}
+
+// The equality of options only looks at the name, this compares everything
+fn same_definition(old: &ConfigOption, new: &ConfigOption) -> bool {
+    old.default == new.default
+        && old.required == new.required
+        && old.takes_argument == new.takes_argument
+        && old.help == new.help
+        && old.documentation == new.documentation
+        && old.list == new.list
+        && old.secret == new.secret
+        && old.deprecated == new.deprecated
+        && old.deprecated_since == new.deprecated_since
+        && old.experimental == new.experimental
+        && old.path == new.path
+        && old.path_kind == new.path_kind
+        && old.requires == new.requires
+        && old.absolute_path == new.absolute_path
+        && old.dedup_list == new.dedup_list
+        && old.address == new.address
+        && old.possible_values == new.possible_values
+        && old.base64 == new.base64
+        && old.non_empty == new.non_empty
+        && old.url_schemes == new.url_schemes
+        && old.list_from_file == new.list_from_file
+        && old.example == new.example
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ConfigOption, Configuration};
+
+    fn configuration(options: &[ConfigOption]) -> Configuration {
+        Configuration {
+            options: options.iter().cloned().collect(),
+            ..Configuration::default()
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let kept = ConfigOption {
+            name: "kept",
+            takes_argument: true,
+            ..ConfigOption::DEFAULT
+        };
+        let removed = ConfigOption {
+            name: "removed",
+            ..ConfigOption::DEFAULT
+        };
+        let added = ConfigOption {
+            name: "added",
+            ..ConfigOption::DEFAULT
+        };
+        let old_port = ConfigOption {
+            name: "port",
+            default: Some("8080"),
+            takes_argument: true,
+            ..ConfigOption::DEFAULT
+        };
+        let new_port = ConfigOption {
+            default: Some("9090"),
+            ..old_port.clone()
+        };
+
+        let old = configuration(&[kept.clone(), removed.clone(), old_port.clone()]);
+        let new = configuration(&[kept, added.clone(), new_port.clone()]);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec![added]);
+        assert_eq!(diff.removed, vec![removed]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].0.default, old_port.default);
+        assert_eq!(diff.changed[0].1.default, new_port.default);
+
+        assert!(old.diff(&old).is_empty());
+    }
+}
@@ -0,0 +1,631 @@
+//! This module generates user facing documentation from a `Configuration`.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use clap::{AppSettings, Shell};
+
+use crate::error::json_string;
+use crate::{ConfigBuilder, ConfigOption, Configuration};
+
+// Replaces the values of secret options when rendering them
+pub(crate) const MASK: &str = "********";
+
+// Renders a single value as it has to be written in the config file, so that it is read back
+// unchanged: a leading `@` is escaped as `@@` and values with line breaks become a heredoc
+fn render_value(name: &str, value: &str) -> String {
+    let value = if value.starts_with('@') {
+        format!("@{}", value)
+    } else {
+        value.to_string()
+    };
+    if !value.contains('\n') {
+        return format!("--{}={}\n", name, value);
+    }
+
+    // The delimiter must not be one of the lines of the value
+    let mut delimiter = String::from("END");
+    while value.lines().any(|line| line.trim() == delimiter) {
+        delimiter.push('_');
+    }
+    format!("--{} <<{}\n{}\n{}\n", name, delimiter, value, delimiter)
+}
+
+impl Configuration {
+    /// Renders resolved values in the format of the config file, so the output can be used as
+    /// a config file again
+    ///
+    /// Options are listed sorted by name, the values of `secret` options are masked and options
+    /// without a value are listed as comments. Values that span multiple lines are written as
+    /// a heredoc.
+    ///
+    /// * `values` The values as they are passed to `Configurable::parse_values`
+    pub fn render_values(&self, values: &HashMap<ConfigOption, Option<Vec<String>>>) -> String {
+        let options = self.sorted_options(|_| true);
+
+        let mut rendered = String::new();
+        for option in options {
+            match values.get(option) {
+                Some(Some(values)) if values.is_empty() => {
+                    rendered.push_str(&format!("--{}\n", option.name));
+                }
+                Some(Some(values)) => {
+                    for value in values {
+                        let value = if option.secret { MASK } else { value };
+                        rendered.push_str(&render_value(option.name, value));
+                    }
+                }
+                _ => rendered.push_str(&format!("# --{} is not set\n", option.name)),
+            }
+        }
+        rendered
+    }
+
+    /// Writes a completion script for the given shell, as generated by clap
+    ///
+    /// The script completes the command `name` of this configuration, so that has to match the
+    /// name of the binary. Options are added sorted by name, so the script is the same on
+    /// every run.
+    ///
+    /// * `shell` The shell to generate completions for
+    /// * `out` Where to write the script to
+    pub fn generate_completions(&self, shell: Shell, mut out: &mut dyn Write) {
+        let help_texts = ConfigBuilder::help_texts(self);
+        ConfigBuilder::create_matcher(self, &help_texts)
+            .gen_completions_to(self.name, shell, &mut out);
+    }
+
+    /// The one line usage string generated by clap without the `USAGE:` title, e.g.
+    /// `tool [FLAGS] [OPTIONS] --user <user>`, to append to short error messages
+    pub fn usage_line(&self) -> String {
+        let mut usage = vec![];
+        let help_texts = ConfigBuilder::help_texts(self);
+        ConfigBuilder::create_matcher(self, &help_texts)
+            .setting(AppSettings::ColorNever)
+            .template("{usage}")
+            .write_help(&mut usage)
+            .expect("writing to a Vec never fails");
+        String::from_utf8_lossy(&usage).trim().to_string()
+    }
+
+    /// Renders a Markdown document describing the application and all of its options
+    ///
+    /// Options are listed sorted by name, for every option the `documentation` is used if
+    /// present and the `help` text otherwise. The `metadata` is listed below the description.
+    pub fn generate_markdown(&self) -> String {
+        let options = self.sorted_options(|_| true);
+
+        let mut markdown = format!("# {}\n\n{}\n\n", self.name, self.about);
+        if !self.metadata.is_empty() {
+            for (key, value) in self.metadata {
+                markdown.push_str(&format!("* {}: {}\n", key, value));
+            }
+            markdown.push('\n');
+        }
+        markdown.push_str("## Options\n");
+        for option in options {
+            markdown.push_str(&option_markdown(self, option));
+        }
+        markdown
+    }
+
+    /// Renders a JSON Schema (draft-07) describing a config file for this configuration as a
+    /// JSON object with one property per option, for validation and completion in editors
+    ///
+    /// Switches are booleans, `list` options are arrays of strings and all other options are
+    /// strings. The `documentation` of an option is used as its description if present and
+    /// the `help` text otherwise. The `metadata` is added as `$comment` in the form
+    /// `key: value`, separated by `; `.
+    pub fn to_json_schema(&self) -> String {
+        let properties: Vec<String> = self
+            .sorted_options(|_| true)
+            .into_iter()
+            .map(|option| {
+                format!(
+                    "{}:{}",
+                    json_string(option.name),
+                    option_schema(self, option)
+                )
+            })
+            .collect();
+        let required: Vec<String> = self
+            .required_options()
+            .into_iter()
+            .map(|option| json_string(option.name))
+            .collect();
+        let comment = if self.metadata.is_empty() {
+            String::new()
+        } else {
+            let metadata: Vec<String> = self
+                .metadata
+                .iter()
+                .map(|(key, value)| format!("{}: {}", key, value))
+                .collect();
+            format!("\"$comment\":{},", json_string(&metadata.join("; ")))
+        };
+        format!(
+            "{{\"$schema\":\"http://json-schema.org/draft-07/schema#\",{}\"title\":{},\
+             \"description\":{},\"type\":\"object\",\"properties\":{{{}}},\
+             \"required\":[{}],\"additionalProperties\":false}}",
+            comment,
+            json_string(self.name),
+            json_string(self.about),
+            properties.join(","),
+            required.join(",")
+        )
+    }
+
+    /// Renders a man page in roff format describing the application and all of its options,
+    /// e.g. for distribution packages
+    ///
+    /// Options are listed sorted by name, like in `generate_markdown`.
+    pub fn generate_man_page(&self) -> String {
+        let options = self.sorted_options(|_| true);
+
+        let mut man = format!(
+            ".TH \"{}\" 1 \"{}\"\n.SH NAME\n{}",
+            roff_escape(&self.name.to_uppercase()),
+            roff_escape(self.version),
+            roff_escape(self.name)
+        );
+        if !self.about.is_empty() {
+            man.push_str(&format!(" \\- {}", roff_escape(self.about)));
+        }
+        man.push_str(&format!(
+            "\n.SH SYNOPSIS\n\\fB{}\\fR [OPTIONS]\n.SH OPTIONS\n",
+            roff_escape(self.name)
+        ));
+        for option in options {
+            man.push_str(&option_man_page(self, option));
+        }
+        man
+    }
+}
+
+fn option_schema(config: &Configuration, option: &ConfigOption) -> String {
+    let mut schema = vec![];
+    match (option.takes_argument, option.list) {
+        (false, _) => schema.push(String::from("\"type\":\"boolean\"")),
+        (true, true) => {
+            schema.push(String::from(
+                "\"type\":\"array\",\"items\":{\"type\":\"string\"}",
+            ));
+            if let Some(default) = option.default {
+                let default = config.expand_default(default);
+                schema.push(format!("\"default\":[{}]", json_string(&default)));
+            }
+        }
+        (true, false) => {
+            schema.push(String::from("\"type\":\"string\""));
+            if let Some(default) = option.default {
+                let default = config.expand_default(default);
+                schema.push(format!("\"default\":{}", json_string(&default)));
+            }
+        }
+    }
+    let description = if option.documentation.is_empty() {
+        option.help
+    } else {
+        option.documentation
+    };
+    if !description.is_empty() {
+        schema.push(format!("\"description\":{}", json_string(description)));
+    }
+    if let Some(example) = option.example {
+        schema.push(format!("\"examples\":[{}]", json_string(example)));
+    }
+    if option.deprecated.is_some() {
+        schema.push(String::from("\"deprecated\":true"));
+    }
+    format!("{{{}}}", schema.join(","))
+}
+
+fn option_markdown(config: &Configuration, option: &ConfigOption) -> String {
+    let mut markdown = format!("\n### `--{}`\n\n", option.name);
+
+    let description = if option.documentation.is_empty() {
+        option.help
+    } else {
+        option.documentation
+    };
+    if !description.is_empty() {
+        markdown.push_str(description);
+        markdown.push_str("\n\n");
+    }
+
+    if option.required {
+        markdown.push_str("* Required\n");
+    }
+    if let (true, Some(default)) = (option.takes_argument, option.default) {
+        markdown.push_str(&format!(
+            "* Default: `{}`\n",
+            config.expand_default(default)
+        ));
+    }
+    if let Some(example) = option.example {
+        markdown.push_str(&format!("* Example: `{}`\n", example));
+    }
+    if option.list {
+        markdown.push_str("* Can be specified multiple times\n");
+    }
+    if option.deprecated.is_some() {
+        markdown.push_str("* Deprecated");
+        if let Some(since) = option.deprecated_since {
+            markdown.push_str(&format!(" since {}", since));
+        }
+        match option.deprecated {
+            Some(hint) if !hint.is_empty() => markdown.push_str(&format!(": {}\n", hint)),
+            _ => markdown.push('\n'),
+        }
+    }
+    markdown
+}
+
+fn option_man_page(config: &Configuration, option: &ConfigOption) -> String {
+    let mut man = format!(".TP\n\\fB\\-\\-{}\\fR", roff_escape(option.name));
+    if option.takes_argument {
+        man.push_str(&format!(" \\fI{}\\fR", roff_escape(option.name)));
+    }
+    man.push('\n');
+
+    let description = if option.documentation.is_empty() {
+        option.help
+    } else {
+        option.documentation
+    };
+    let mut lines: Vec<String> = description.lines().map(roff_escape).collect();
+    if option.required {
+        lines.push(String::from("Required."));
+    }
+    if let (true, Some(default)) = (option.takes_argument, option.default) {
+        lines.push(format!(
+            "Default: {}",
+            roff_escape(&config.expand_default(default))
+        ));
+    }
+    if let Some(example) = option.example {
+        lines.push(format!("Example: {}", roff_escape(example)));
+    }
+    if option.list {
+        lines.push(String::from("Can be specified multiple times."));
+    }
+    if let Some(warning) = option.deprecation_warning() {
+        lines.push(roff_escape(&warning));
+    }
+    for line in lines {
+        man.push_str(&line);
+        man.push('\n');
+    }
+    man
+}
+
+// Escapes text so roff does not interpret it, lines starting with a control character are
+// protected with a zero width character
+fn roff_escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prompt::NonInteractive;
+    use crate::{
+        parse_config_reader, ConfigBuilder, ConfigOption, ConfigSource, Configuration, Shell,
+    };
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+
+    #[test]
+    fn bash_completions() {
+        let configuration = Configuration {
+            name: "testtool",
+            options: [ConfigOption {
+                name: "testparam",
+                takes_argument: true,
+                ..ConfigOption::DEFAULT
+            }]
+            .iter()
+            .cloned()
+            .collect(),
+            ..Configuration::default()
+        };
+
+        let mut out = vec![];
+        configuration.generate_completions(Shell::Bash, &mut out);
+        let script = String::from_utf8(out).expect("Completions are not valid UTF-8");
+
+        assert!(script.contains("testtool"));
+        assert!(script.contains("--testparam"));
+    }
+
+    // The options are stored in a HashSet, every new configuration iterates them in another
+    // order, but the generated output has to be the same
+    #[test]
+    fn stable_output() {
+        fn configuration() -> Configuration {
+            Configuration {
+                name: "testtool",
+                options: [
+                    "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf",
+                ]
+                .iter()
+                .map(|name| ConfigOption {
+                    name,
+                    takes_argument: true,
+                    help: name,
+                    ..ConfigOption::DEFAULT
+                })
+                .collect(),
+                ..Configuration::default()
+            }
+        }
+        fn completions(configuration: &Configuration) -> Vec<u8> {
+            let mut out = vec![];
+            configuration.generate_completions(Shell::Bash, &mut out);
+            out
+        }
+
+        let (first, second) = (configuration(), configuration());
+        assert_eq!(completions(&first), completions(&second));
+        assert_eq!(first.to_json_schema(), second.to_json_schema());
+        assert_eq!(first.generate_man_page(), second.generate_man_page());
+        assert_eq!(first.generate_markdown(), second.generate_markdown());
+    }
+
+    #[test]
+    fn usage_line() {
+        let configuration = Configuration {
+            name: "testtool",
+            options: [
+                ConfigOption {
+                    name: "user",
+                    takes_argument: true,
+                    required: true,
+                    ..ConfigOption::DEFAULT
+                },
+                ConfigOption {
+                    name: "verbose",
+                    ..ConfigOption::DEFAULT
+                },
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            ..Configuration::default()
+        };
+
+        let usage = configuration.usage_line();
+        assert!(usage.starts_with("testtool "), "{}", usage);
+        assert!(usage.contains("--user <user>"), "{}", usage);
+        assert!(usage.contains("[FLAGS]"), "{}", usage);
+        assert!(!usage.contains("--verbose"), "{}", usage);
+        assert!(!usage.contains('\n'), "{}", usage);
+    }
+
+    #[test]
+    fn render_values() {
+        let user = ConfigOption {
+            name: "user",
+            takes_argument: true,
+            ..ConfigOption::DEFAULT
+        };
+        let password = ConfigOption {
+            name: "password",
+            takes_argument: true,
+            secret: true,
+            ..ConfigOption::DEFAULT
+        };
+        let verbose = ConfigOption {
+            name: "verbose",
+            ..ConfigOption::DEFAULT
+        };
+        let configuration = Configuration {
+            options: [user.clone(), password.clone(), verbose.clone()]
+                .iter()
+                .cloned()
+                .collect(),
+            ..Configuration::default()
+        };
+
+        let values: HashMap<ConfigOption, Option<Vec<String>>> = [
+            (user, None),
+            (password, Some(vec![String::from("hunter2")])),
+            (verbose, Some(vec![])),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        assert_eq!(
+            configuration.render_values(&values),
+            "--password=********\n# --user is not set\n--verbose\n"
+        );
+    }
+
+    // Test that rendered values are read back unchanged, including values that start with `@`
+    // or span multiple lines
+    #[test]
+    fn render_values_round_trip() {
+        let user = ConfigOption {
+            name: "user",
+            takes_argument: true,
+            ..ConfigOption::DEFAULT
+        };
+        let cert = ConfigOption {
+            name: "cert",
+            takes_argument: true,
+            list: true,
+            ..ConfigOption::DEFAULT
+        };
+        let configuration = Configuration {
+            options: [user.clone(), cert.clone()].iter().cloned().collect(),
+            name: "Test Tool",
+            version: "0.1",
+            ..Configuration::default()
+        };
+
+        let values: HashMap<ConfigOption, Option<Vec<String>>> = [
+            (user.clone(), Some(vec![String::from("@admin")])),
+            (
+                cert.clone(),
+                Some(vec![
+                    String::from("-----BEGIN-----\n  indented # no comment\n\nEND\n-----END-----"),
+                    String::from("@first\nsecond"),
+                ]),
+            ),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        let rendered = configuration.render_values(&values);
+        assert!(rendered.contains("--user=@@admin\n"), "{}", rendered);
+        assert!(rendered.contains("--cert <<END_\n"), "{}", rendered);
+
+        let args = parse_config_reader(rendered.as_bytes()).expect("Error parsing rendered values");
+        let resolved = ConfigBuilder::resolve_from(
+            &configuration,
+            vec![OsString::from("testtool")],
+            ConfigSource::Args(&args),
+            &mut NonInteractive,
+            false,
+        )
+        .expect("Error resolving rendered values");
+        assert_eq!(resolved.values.get(&user), values.get(&user));
+        assert_eq!(resolved.values.get(&cert), values.get(&cert));
+    }
+
+    #[test]
+    fn json_schema() {
+        let configuration = Configuration {
+            name: "testtool",
+            about: "blabla",
+            options: [
+                ConfigOption {
+                    name: "user",
+                    required: true,
+                    takes_argument: true,
+                    help: "The \"user\" to log in as",
+                    ..ConfigOption::DEFAULT
+                },
+                ConfigOption {
+                    name: "tag",
+                    default: Some("default"),
+                    takes_argument: true,
+                    list: true,
+                    documentation: "Tags to add",
+                    ..ConfigOption::DEFAULT
+                },
+                ConfigOption {
+                    name: "verbose",
+                    ..ConfigOption::DEFAULT
+                },
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            ..Configuration::default()
+        };
+
+        assert_eq!(
+            configuration.to_json_schema(),
+            r#"{"$schema":"http://json-schema.org/draft-07/schema#","title":"testtool","description":"blabla","type":"object","properties":{"#.to_owned()
+                + r#""tag":{"type":"array","items":{"type":"string"},"default":["default"],"description":"Tags to add"},"#
+                + r#""user":{"type":"string","description":"The \"user\" to log in as"},"#
+                + r#""verbose":{"type":"boolean"}},"required":["user"],"additionalProperties":false}"#
+        );
+    }
+
+    #[test]
+    fn man_page() {
+        let configuration = Configuration {
+            name: "testtool",
+            version: "0.1",
+            about: "blabla",
+            options: [ConfigOption {
+                name: "log-dir",
+                default: Some("/var/log"),
+                takes_argument: true,
+                help: "Where to write logs",
+                ..ConfigOption::DEFAULT
+            }]
+            .iter()
+            .cloned()
+            .collect(),
+            ..Configuration::default()
+        };
+
+        assert_eq!(
+            configuration.generate_man_page(),
+            ".TH \"TESTTOOL\" 1 \"0.1\"\n.SH NAME\ntesttool \\- blabla\n\
+            .SH SYNOPSIS\n\\fBtesttool\\fR [OPTIONS]\n.SH OPTIONS\n\
+            .TP\n\\fB\\-\\-log\\-dir\\fR \\fIlog\\-dir\\fR\n\
+            Where to write logs\nDefault: /var/log\n"
+        );
+    }
+
+    #[test]
+    fn markdown() {
+        let configuration = Configuration {
+            name: "Test Tool",
+            version: "0.1",
+            about: "blabla",
+            options: [
+                ConfigOption {
+                    name: "foo",
+                    takes_argument: true,
+                    help: "Old option",
+                    deprecated: Some("use 'bar'"),
+                    deprecated_since: Some("1.2.0"),
+                    ..ConfigOption::DEFAULT
+                },
+                ConfigOption {
+                    name: "bar",
+                    default: Some("42"),
+                    takes_argument: true,
+                    help: "New option",
+                    documentation: "The replacement for foo",
+                    example: Some("7"),
+                    ..ConfigOption::DEFAULT
+                },
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            ..Configuration::default()
+        };
+
+        assert_eq!(
+            configuration.generate_markdown(),
+            "# Test Tool\n\nblabla\n\n## Options\n\
+            \n### `--bar`\n\nThe replacement for foo\n\n* Default: `42`\n* Example: `7`\n\
+            \n### `--foo`\n\nOld option\n\n* Deprecated since 1.2.0: use 'bar'\n"
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        let configuration = Configuration {
+            name: "testtool",
+            about: "blabla",
+            metadata: &[
+                ("docs", "https://docs.example.com/testtool"),
+                ("category", "operator"),
+            ],
+            ..Configuration::default()
+        };
+
+        assert_eq!(
+            configuration.generate_markdown(),
+            "# testtool\n\nblabla\n\n* docs: https://docs.example.com/testtool\n\
+            * category: operator\n\n## Options\n"
+        );
+        assert!(configuration.to_json_schema().starts_with(
+            r#"{"$schema":"http://json-schema.org/draft-07/schema#","$comment":"docs: https://docs.example.com/testtool; category: operator","title":"testtool","#
+        ));
+    }
+}
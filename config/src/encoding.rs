@@ -0,0 +1,109 @@
+//! This module contains the encodings that are supported for config files.
+
+use std::fmt::{Display, Formatter};
+
+/// The encoding of the config file, its content is transcoded to UTF-8 before the arguments
+/// are parsed
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ConfigFileEncoding {
+    /// The file is read as raw bytes, which allows arguments that are not valid UTF-8 on
+    /// Unix-like systems
+    #[default]
+    Utf8,
+    /// ISO-8859-1, every byte is mapped to the Unicode code point with the same value
+    Latin1,
+    /// UTF-16 little endian, a leading byte order mark is ignored
+    Utf16Le,
+    /// UTF-16 big endian, a leading byte order mark is ignored
+    Utf16Be,
+}
+
+impl ConfigFileEncoding {
+    // Transcodes the content of a config file to UTF-8, content in the default encoding is
+    // returned as is, the error is the offset of the first byte that could not be decoded
+    pub(crate) fn decode(&self, bytes: Vec<u8>) -> Result<Vec<u8>, usize> {
+        let units: Vec<u16> = match self {
+            ConfigFileEncoding::Utf8 => return Ok(bytes),
+            ConfigFileEncoding::Latin1 => {
+                return Ok(bytes
+                    .into_iter()
+                    .map(char::from)
+                    .collect::<String>()
+                    .into_bytes())
+            }
+            ConfigFileEncoding::Utf16Le | ConfigFileEncoding::Utf16Be => {
+                if !bytes.len().is_multiple_of(2) {
+                    return Err(bytes.len() - 1);
+                }
+                bytes
+                    .chunks(2)
+                    .map(|pair| match self {
+                        ConfigFileEncoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                        _ => u16::from_be_bytes([pair[0], pair[1]]),
+                    })
+                    .collect()
+            }
+        };
+        let (units, mut offset) = match units.strip_prefix(&[0xFEFF]) {
+            Some(units) => (units, 2),
+            None => (&units[..], 0),
+        };
+        let mut decoded = String::with_capacity(units.len());
+        for c in char::decode_utf16(units.iter().copied()) {
+            let c = c.map_err(|_| offset)?;
+            decoded.push(c);
+            offset += 2 * c.len_utf16();
+        }
+        Ok(decoded.into_bytes())
+    }
+}
+
+impl Display for ConfigFileEncoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ConfigFileEncoding::Utf8 => "UTF-8",
+            ConfigFileEncoding::Latin1 => "ISO-8859-1",
+            ConfigFileEncoding::Utf16Le => "UTF-16LE",
+            ConfigFileEncoding::Utf16Be => "UTF-16BE",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConfigFileEncoding;
+
+    #[test]
+    fn decode() {
+        assert_eq!(
+            ConfigFileEncoding::Utf8.decode(b"--foo=\xFF".to_vec()),
+            Ok(b"--foo=\xFF".to_vec())
+        );
+        assert_eq!(
+            ConfigFileEncoding::Latin1.decode(b"--foo=gr\xFC\xDFe".to_vec()),
+            Ok("--foo=grüße".as_bytes().to_vec())
+        );
+        assert_eq!(
+            ConfigFileEncoding::Utf16Le.decode(b"\xFF\xFE-\0\xFC\0".to_vec()),
+            Ok("-ü".as_bytes().to_vec())
+        );
+        assert_eq!(
+            ConfigFileEncoding::Utf16Be.decode(b"\0-\0\xFC".to_vec()),
+            Ok("-ü".as_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert_eq!(
+            ConfigFileEncoding::Utf16Le.decode(b"-\0\xFC".to_vec()),
+            Err(2)
+        );
+        // An unpaired surrogate after the byte order mark and a character
+        assert_eq!(
+            ConfigFileEncoding::Utf16Le.decode(b"\xFF\xFE-\0\x00\xD8-\0".to_vec()),
+            Err(4)
+        );
+    }
+}
@@ -0,0 +1,677 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+use crate::{ConfigFileEncoding, PathKind};
+
+/// All errors that can occur when building a configuration object from the command line
+/// arguments and an optional config file
+#[derive(Debug)]
+pub enum ConfigError {
+    /// An option that is marked as required was neither specified on the command line nor in
+    /// the config file (and could not be prompted for)
+    MissingRequired { name: String },
+    /// The value of an option is not valid UTF-8, only `path` options can have such values
+    InvalidUtf8 { name: String },
+    /// An option that is marked as experimental was used without `--enable-experimental`
+    ExperimentalNotEnabled { name: String },
+    /// A switch in the config file was given a value that is not a boolean, the accepted
+    /// values are `true`/`false`, `yes`/`no`, `on`/`off` and `1`/`0`
+    InvalidBool { name: String, value: String },
+    /// The value of an option could not be parsed as a number, see `ResolvedValues::get_number`
+    InvalidNumber { name: String, value: String },
+    /// A value of an option could not be parsed as `key=value`, see `ResolvedValues::get_map`
+    InvalidMapEntry { name: String, value: String },
+    /// The value of an option could not be parsed as a network address, see
+    /// `ResolvedValues::get_socket_addr`
+    InvalidAddress { name: String, value: String },
+    /// The value of an option is not valid base64, see `ResolvedValues::get_bytes_b64`
+    InvalidBase64 { name: String },
+    /// An option that is not a `list` option was specified more than once on the command
+    /// line, see `Configuration::reject_duplicate_flags`
+    DuplicateFlag { name: String },
+    /// The value of an option is not a URL or its scheme is not allowed, see
+    /// `ResolvedValues::get_url`
+    InvalidUrl { name: String, message: String },
+    /// The value of an option with `ConfigOption::non_empty` set is empty or only whitespace
+    EmptyValue { name: String },
+    /// The value of an option could not be parsed into an enum, see `ResolvedValues::get_enum`
+    InvalidEnumValue {
+        name: String,
+        value: String,
+        possible_values: &'static [&'static str],
+    },
+    /// The value of an option referred to a file with `@<file>`, but that file could not be read
+    ValueFileUnreadable {
+        name: String,
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// Nothing was written to the named pipe an option value is read from with `@<file>` within
+    /// `Configuration::fifo_timeout`
+    SecretSourceTimeout { name: String, path: PathBuf },
+    /// The value of an option with a `path_kind` does not match the expectation
+    InvalidPath {
+        name: String,
+        path: PathBuf,
+        expected: PathKind,
+    },
+    /// The config file specified in the environment variable could not be opened and
+    /// `Configuration::strict_config_file` is set
+    ConfigFileNotFound { path: PathBuf, source: io::Error },
+    /// The config file contains bytes that are not valid in `Configuration::config_file_encoding`,
+    /// `offset` is the position of the first of them
+    InvalidEncoding {
+        path: PathBuf,
+        encoding: ConfigFileEncoding,
+        offset: usize,
+    },
+    /// The config passed to `ConfigBuilder::build_from_reader` or the JSON in
+    /// `Configuration::json_env` could not be read or parsed
+    InvalidConfigContent { message: String },
+    /// A value in the config file refers to an option that is not set before it, see
+    /// `Configuration::interpolate_config_file`
+    UndefinedReference { name: String, reference: String },
+    /// The config file can be accessed by group or others, but `Configuration::private_config_file`
+    /// is set, `mode` contains the permission bits of the file
+    InsecureConfigPermissions { path: PathBuf, mode: u32 },
+    /// The current working directory, which relative paths are resolved against, could not be
+    /// determined
+    WorkingDirectory(io::Error),
+    /// Prompting the user for the value of a missing option failed
+    PromptFailed { name: String, source: io::Error },
+    /// The name or version of the `Configuration` is empty, see `Configuration::validate`
+    InvalidMetadata { field: &'static str },
+    /// An option (in `requires`) or a group (in `Configuration::required_one_of`) refers to an
+    /// option that does not exist, see `Configuration::validate`
+    DanglingReference {
+        from: &'static str,
+        to: &'static str,
+    },
+    /// A group in `Configuration::required_one_of` has the name of an option, clap can't tell
+    /// them apart, see `Configuration::validate`
+    GroupNameClash { name: &'static str },
+    /// Clap rejected the arguments, this contains the error message as generated by clap
+    InvalidArguments(clap::Error),
+    /// The implementation of `Configurable::parse_values` (or `parse_resolved`) returned an
+    /// error
+    ParseValues(anyhow::Error),
+}
+
+impl ConfigError {
+    /// The name of the variant, this is stable and meant to be used by tooling
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ConfigError::MissingRequired { .. } => "MissingRequired",
+            ConfigError::InvalidUtf8 { .. } => "InvalidUtf8",
+            ConfigError::ExperimentalNotEnabled { .. } => "ExperimentalNotEnabled",
+            ConfigError::InvalidBool { .. } => "InvalidBool",
+            ConfigError::InvalidNumber { .. } => "InvalidNumber",
+            ConfigError::InvalidMapEntry { .. } => "InvalidMapEntry",
+            ConfigError::InvalidAddress { .. } => "InvalidAddress",
+            ConfigError::InvalidEnumValue { .. } => "InvalidEnumValue",
+            ConfigError::InvalidBase64 { .. } => "InvalidBase64",
+            ConfigError::EmptyValue { .. } => "EmptyValue",
+            ConfigError::InvalidUrl { .. } => "InvalidUrl",
+            ConfigError::DuplicateFlag { .. } => "DuplicateFlag",
+            ConfigError::ValueFileUnreadable { .. } => "ValueFileUnreadable",
+            ConfigError::SecretSourceTimeout { .. } => "SecretSourceTimeout",
+            ConfigError::InvalidPath { .. } => "InvalidPath",
+            ConfigError::ConfigFileNotFound { .. } => "ConfigFileNotFound",
+            ConfigError::InvalidConfigContent { .. } => "InvalidConfigContent",
+            ConfigError::InvalidEncoding { .. } => "InvalidEncoding",
+            ConfigError::UndefinedReference { .. } => "UndefinedReference",
+            ConfigError::InsecureConfigPermissions { .. } => "InsecureConfigPermissions",
+            ConfigError::WorkingDirectory(_) => "WorkingDirectory",
+            ConfigError::PromptFailed { .. } => "PromptFailed",
+            ConfigError::InvalidMetadata { .. } => "InvalidMetadata",
+            ConfigError::DanglingReference { .. } => "DanglingReference",
+            ConfigError::GroupNameClash { .. } => "GroupNameClash",
+            ConfigError::InvalidArguments(_) => "InvalidArguments",
+            ConfigError::ParseValues(_) => "ParseValues",
+        }
+    }
+
+    /// A numeric code for this error, which operators can look up the same way as the error
+    /// codes of the tools themselves, it is rendered as e.g. `E0001` with `{:#}`
+    ///
+    /// The codes are stable, new variants get the next free code and codes are never reused.
+    pub fn code(&self) -> u32 {
+        match self {
+            ConfigError::MissingRequired { .. } => 1,
+            ConfigError::InvalidUtf8 { .. } => 2,
+            ConfigError::ExperimentalNotEnabled { .. } => 3,
+            ConfigError::InvalidBool { .. } => 4,
+            ConfigError::InvalidNumber { .. } => 5,
+            ConfigError::InvalidMapEntry { .. } => 6,
+            ConfigError::InvalidAddress { .. } => 7,
+            ConfigError::InvalidEnumValue { .. } => 8,
+            ConfigError::InvalidBase64 { .. } => 9,
+            ConfigError::ValueFileUnreadable { .. } => 10,
+            ConfigError::SecretSourceTimeout { .. } => 11,
+            ConfigError::InvalidPath { .. } => 12,
+            ConfigError::ConfigFileNotFound { .. } => 13,
+            ConfigError::InvalidConfigContent { .. } => 14,
+            ConfigError::InsecureConfigPermissions { .. } => 15,
+            ConfigError::WorkingDirectory(_) => 16,
+            ConfigError::PromptFailed { .. } => 17,
+            ConfigError::InvalidMetadata { .. } => 18,
+            ConfigError::DanglingReference { .. } => 19,
+            ConfigError::InvalidArguments(_) => 20,
+            ConfigError::ParseValues(_) => 21,
+            ConfigError::UndefinedReference { .. } => 22,
+            ConfigError::EmptyValue { .. } => 23,
+            ConfigError::InvalidUrl { .. } => 24,
+            ConfigError::DuplicateFlag { .. } => 25,
+            ConfigError::InvalidEncoding { .. } => 26,
+            ConfigError::GroupNameClash { .. } => 27,
+        }
+    }
+
+    /// The name of the option this error is about, if it is about a single option
+    pub fn option(&self) -> Option<&str> {
+        match self {
+            ConfigError::MissingRequired { name }
+            | ConfigError::InvalidUtf8 { name }
+            | ConfigError::ExperimentalNotEnabled { name }
+            | ConfigError::InvalidBool { name, .. }
+            | ConfigError::InvalidNumber { name, .. }
+            | ConfigError::InvalidMapEntry { name, .. }
+            | ConfigError::InvalidAddress { name, .. }
+            | ConfigError::InvalidEnumValue { name, .. }
+            | ConfigError::InvalidBase64 { name }
+            | ConfigError::EmptyValue { name }
+            | ConfigError::InvalidUrl { name, .. }
+            | ConfigError::DuplicateFlag { name }
+            | ConfigError::ValueFileUnreadable { name, .. }
+            | ConfigError::SecretSourceTimeout { name, .. }
+            | ConfigError::InvalidPath { name, .. }
+            | ConfigError::UndefinedReference { name, .. }
+            | ConfigError::PromptFailed { name, .. } => Some(name),
+            ConfigError::ConfigFileNotFound { .. }
+            | ConfigError::InvalidConfigContent { .. }
+            | ConfigError::InvalidEncoding { .. }
+            | ConfigError::InsecureConfigPermissions { .. }
+            | ConfigError::WorkingDirectory(_)
+            | ConfigError::InvalidMetadata { .. }
+            | ConfigError::DanglingReference { .. }
+            | ConfigError::GroupNameClash { .. }
+            | ConfigError::InvalidArguments(_)
+            | ConfigError::ParseValues(_) => None,
+        }
+    }
+
+    /// Renders this error as a JSON object for tools that wrap our binaries, e.g.
+    /// `{"kind":"MissingRequired","code":1,"option":"foo","message":"required option 'foo' was not provided"}`
+    ///
+    /// All four fields are always present, `option` is `null` if the error is not about a
+    /// single option. Trailing whitespace (like the newline in clap messages) is removed from
+    /// the message.
+    pub fn to_json(&self) -> String {
+        let option = match self.option() {
+            Some(option) => json_string(option),
+            None => String::from("null"),
+        };
+        format!(
+            "{{\"kind\":{},\"code\":{},\"option\":{},\"message\":{}}}",
+            json_string(self.kind()),
+            self.code(),
+            option,
+            json_string(self.to_string().trim_end())
+        )
+    }
+}
+
+// Quotes and escapes a string so it can be used as a JSON value
+pub(crate) fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // `{:#}` prefixes the message with the code, e.g. for log messages
+        if f.alternate() {
+            write!(f, "E{:04}: ", self.code())?;
+        }
+        match self {
+            ConfigError::MissingRequired { name } => {
+                write!(f, "required option '{}' was not provided", name)
+            }
+            ConfigError::InvalidUtf8 { name } => {
+                write!(f, "value of option '{}' is not valid UTF-8", name)
+            }
+            ConfigError::ExperimentalNotEnabled { name } => write!(
+                f,
+                "option '{}' is experimental and requires --enable-experimental",
+                name
+            ),
+            ConfigError::InvalidBool { name, value } => {
+                write!(f, "value '{}' of switch '{}' is not a boolean", value, name)
+            }
+            ConfigError::InvalidNumber { name, value } => {
+                write!(
+                    f,
+                    "value '{}' of option '{}' is not a valid number",
+                    value, name
+                )
+            }
+            ConfigError::InvalidMapEntry { name, value } => write!(
+                f,
+                "value '{}' of option '{}' is not of the form key=value",
+                value, name
+            ),
+            ConfigError::InvalidAddress { name, value } => write!(
+                f,
+                "value '{}' of option '{}' is not a valid address",
+                value, name
+            ),
+            ConfigError::InvalidBase64 { name } => {
+                write!(f, "value of option '{}' is not valid base64", name)
+            }
+            ConfigError::DuplicateFlag { name } => {
+                write!(f, "option '{}' was specified more than once", name)
+            }
+            ConfigError::InvalidUrl { name, message } => {
+                write!(f, "invalid value of option '{}': {}", name, message)
+            }
+            ConfigError::EmptyValue { name } => {
+                write!(f, "value of option '{}' must not be empty", name)
+            }
+            ConfigError::InvalidEnumValue {
+                name,
+                value,
+                possible_values,
+            } => {
+                write!(f, "value '{}' of option '{}' is not valid", value, name)?;
+                if !possible_values.is_empty() {
+                    write!(f, ", possible values: {}", possible_values.join(", "))?;
+                }
+                Ok(())
+            }
+            ConfigError::ValueFileUnreadable { name, path, source } => write!(
+                f,
+                "failed to read value of option '{}' from '{}': {}",
+                name,
+                path.display(),
+                source
+            ),
+            ConfigError::SecretSourceTimeout { name, path } => write!(
+                f,
+                "timed out waiting for the value of option '{}' from '{}'",
+                name,
+                path.display()
+            ),
+            ConfigError::InvalidPath {
+                name,
+                path,
+                expected,
+            } => write!(
+                f,
+                "value '{}' of option '{}' is not {}",
+                path.display(),
+                name,
+                expected
+            ),
+            ConfigError::UndefinedReference { name, reference } => write!(
+                f,
+                "value of option '{}' refers to '{}', which is not set before it",
+                name, reference
+            ),
+            ConfigError::InvalidConfigContent { message } => {
+                write!(f, "failed to read config: {}", message)
+            }
+            ConfigError::InvalidEncoding {
+                path,
+                encoding,
+                offset,
+            } => write!(
+                f,
+                "config file '{}' is not valid {}, the first invalid byte is at offset {}",
+                path.display(),
+                encoding,
+                offset
+            ),
+            ConfigError::ConfigFileNotFound { path, source } => {
+                write!(
+                    f,
+                    "failed to open config file '{}': {}",
+                    path.display(),
+                    source
+                )
+            }
+            ConfigError::InsecureConfigPermissions { path, mode } => write!(
+                f,
+                "config file '{}' has mode {:o}, it must not be accessible by group or others",
+                path.display(),
+                mode
+            ),
+            ConfigError::WorkingDirectory(err) => {
+                write!(f, "failed to determine the working directory: {}", err)
+            }
+            ConfigError::PromptFailed { name, source } => {
+                write!(f, "failed to read value for option '{}': {}", name, source)
+            }
+            ConfigError::InvalidMetadata { field } => {
+                write!(f, "the {} of the configuration must not be empty", field)
+            }
+            ConfigError::DanglingReference { from, to } => {
+                write!(f, "'{}' refers to the unknown option '{}'", from, to)
+            }
+            ConfigError::GroupNameClash { name } => {
+                write!(f, "the group '{}' has the same name as an option", name)
+            }
+            ConfigError::InvalidArguments(err) => write!(f, "{}", err),
+            ConfigError::ParseValues(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::PromptFailed { source, .. }
+            | ConfigError::ConfigFileNotFound { source, .. }
+            | ConfigError::ValueFileUnreadable { source, .. } => Some(source),
+            ConfigError::WorkingDirectory(err) => Some(err),
+            ConfigError::InvalidArguments(err) => Some(err),
+            ConfigError::ParseValues(err) => Some(err.as_ref()),
+            ConfigError::MissingRequired { .. }
+            | ConfigError::InvalidUtf8 { .. }
+            | ConfigError::ExperimentalNotEnabled { .. }
+            | ConfigError::InvalidBool { .. }
+            | ConfigError::InvalidNumber { .. }
+            | ConfigError::InvalidMapEntry { .. }
+            | ConfigError::InvalidAddress { .. }
+            | ConfigError::InvalidEnumValue { .. }
+            | ConfigError::InvalidBase64 { .. }
+            | ConfigError::EmptyValue { .. }
+            | ConfigError::InvalidUrl { .. }
+            | ConfigError::DuplicateFlag { .. }
+            | ConfigError::InvalidEncoding { .. }
+            | ConfigError::SecretSourceTimeout { .. }
+            | ConfigError::InvalidPath { .. }
+            | ConfigError::InvalidConfigContent { .. }
+            | ConfigError::UndefinedReference { .. }
+            | ConfigError::InsecureConfigPermissions { .. }
+            | ConfigError::InvalidMetadata { .. }
+            | ConfigError::DanglingReference { .. }
+            | ConfigError::GroupNameClash { .. } => None,
+        }
+    }
+}
+
+/// A line of a config file that could not be parsed and was skipped, the remaining lines are
+/// used nonetheless, see `ResolvedValues::warnings`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConfigWarning {
+    /// The config file that contains the line
+    pub file: PathBuf,
+    /// The number of the line, starting at 1
+    pub line: usize,
+    /// What is wrong with the line
+    pub message: String,
+}
+
+impl Display for ConfigWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.message)
+    }
+}
+
+impl From<clap::Error> for ConfigError {
+    fn from(err: clap::Error) -> Self {
+        ConfigError::InvalidArguments(err)
+    }
+}
+
+impl From<anyhow::Error> for ConfigError {
+    fn from(err: anyhow::Error) -> Self {
+        ConfigError::ParseValues(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConfigError;
+    use crate::{ConfigFileEncoding, PathKind};
+    use std::io;
+    use std::path::PathBuf;
+
+    fn named(name: &str) -> String {
+        name.to_string()
+    }
+
+    // One error of every kind with its JSON representation
+    fn cases() -> Vec<(ConfigError, &'static str)> {
+        vec![
+            (
+                ConfigError::MissingRequired { name: named("foo") },
+                r#"{"kind":"MissingRequired","code":1,"option":"foo","message":"required option 'foo' was not provided"}"#,
+            ),
+            (
+                ConfigError::InvalidUtf8 { name: named("foo") },
+                r#"{"kind":"InvalidUtf8","code":2,"option":"foo","message":"value of option 'foo' is not valid UTF-8"}"#,
+            ),
+            (
+                ConfigError::ExperimentalNotEnabled { name: named("foo") },
+                r#"{"kind":"ExperimentalNotEnabled","code":3,"option":"foo","message":"option 'foo' is experimental and requires --enable-experimental"}"#,
+            ),
+            (
+                ConfigError::InvalidBool {
+                    name: named("foo"),
+                    value: named("maybe"),
+                },
+                r#"{"kind":"InvalidBool","code":4,"option":"foo","message":"value 'maybe' of switch 'foo' is not a boolean"}"#,
+            ),
+            (
+                ConfigError::InvalidNumber {
+                    name: named("foo"),
+                    value: named("1__000"),
+                },
+                r#"{"kind":"InvalidNumber","code":5,"option":"foo","message":"value '1__000' of option 'foo' is not a valid number"}"#,
+            ),
+            (
+                ConfigError::InvalidMapEntry {
+                    name: named("label"),
+                    value: named("prod"),
+                },
+                r#"{"kind":"InvalidMapEntry","code":6,"option":"label","message":"value 'prod' of option 'label' is not of the form key=value"}"#,
+            ),
+            (
+                ConfigError::InvalidAddress {
+                    name: named("listen-address"),
+                    value: named("localhost"),
+                },
+                r#"{"kind":"InvalidAddress","code":7,"option":"listen-address","message":"value 'localhost' of option 'listen-address' is not a valid address"}"#,
+            ),
+            (
+                ConfigError::InvalidEnumValue {
+                    name: named("mode"),
+                    value: named("fast"),
+                    possible_values: &["safe", "unsafe"],
+                },
+                r#"{"kind":"InvalidEnumValue","code":8,"option":"mode","message":"value 'fast' of option 'mode' is not valid, possible values: safe, unsafe"}"#,
+            ),
+            (
+                ConfigError::InvalidBase64 {
+                    name: named("token"),
+                },
+                r#"{"kind":"InvalidBase64","code":9,"option":"token","message":"value of option 'token' is not valid base64"}"#,
+            ),
+            (
+                ConfigError::EmptyValue {
+                    name: named("name"),
+                },
+                r#"{"kind":"EmptyValue","code":23,"option":"name","message":"value of option 'name' must not be empty"}"#,
+            ),
+            (
+                ConfigError::InvalidUrl {
+                    name: named("endpoint"),
+                    message: named("'https:///api' is not a valid URL: the host is missing"),
+                },
+                r#"{"kind":"InvalidUrl","code":24,"option":"endpoint","message":"invalid value of option 'endpoint': 'https:///api' is not a valid URL: the host is missing"}"#,
+            ),
+            (
+                ConfigError::DuplicateFlag {
+                    name: named("testparam"),
+                },
+                r#"{"kind":"DuplicateFlag","code":25,"option":"testparam","message":"option 'testparam' was specified more than once"}"#,
+            ),
+            (
+                ConfigError::InvalidEncoding {
+                    path: PathBuf::from("/etc/tool.conf"),
+                    encoding: ConfigFileEncoding::Utf16Le,
+                    offset: 6,
+                },
+                r#"{"kind":"InvalidEncoding","code":26,"option":null,"message":"config file '/etc/tool.conf' is not valid UTF-16LE, the first invalid byte is at offset 6"}"#,
+            ),
+            (
+                ConfigError::ValueFileUnreadable {
+                    name: named("foo"),
+                    path: PathBuf::from("/ciphers"),
+                    source: io::Error::new(io::ErrorKind::NotFound, "not found"),
+                },
+                r#"{"kind":"ValueFileUnreadable","code":10,"option":"foo","message":"failed to read value of option 'foo' from '/ciphers': not found"}"#,
+            ),
+            (
+                ConfigError::SecretSourceTimeout {
+                    name: named("foo"),
+                    path: PathBuf::from("/run/secret"),
+                },
+                r#"{"kind":"SecretSourceTimeout","code":11,"option":"foo","message":"timed out waiting for the value of option 'foo' from '/run/secret'"}"#,
+            ),
+            (
+                ConfigError::InvalidPath {
+                    name: named("foo"),
+                    path: PathBuf::from("/missing"),
+                    expected: PathKind::ExistingFile,
+                },
+                r#"{"kind":"InvalidPath","code":12,"option":"foo","message":"value '/missing' of option 'foo' is not an existing file"}"#,
+            ),
+            (
+                ConfigError::ConfigFileNotFound {
+                    path: PathBuf::from("/etc/tool.conf"),
+                    source: io::Error::new(io::ErrorKind::NotFound, "not found"),
+                },
+                r#"{"kind":"ConfigFileNotFound","code":13,"option":null,"message":"failed to open config file '/etc/tool.conf': not found"}"#,
+            ),
+            (
+                ConfigError::UndefinedReference {
+                    name: named("log-dir"),
+                    reference: named("base-dir"),
+                },
+                r#"{"kind":"UndefinedReference","code":22,"option":"log-dir","message":"value of option 'log-dir' refers to 'base-dir', which is not set before it"}"#,
+            ),
+            (
+                ConfigError::InvalidConfigContent {
+                    message: named("1: heredoc is not terminated by 'END'"),
+                },
+                r#"{"kind":"InvalidConfigContent","code":14,"option":null,"message":"failed to read config: 1: heredoc is not terminated by 'END'"}"#,
+            ),
+            (
+                ConfigError::InsecureConfigPermissions {
+                    path: PathBuf::from("/etc/tool.conf"),
+                    mode: 0o644,
+                },
+                r#"{"kind":"InsecureConfigPermissions","code":15,"option":null,"message":"config file '/etc/tool.conf' has mode 644, it must not be accessible by group or others"}"#,
+            ),
+            (
+                ConfigError::WorkingDirectory(io::Error::other("removed")),
+                r#"{"kind":"WorkingDirectory","code":16,"option":null,"message":"failed to determine the working directory: removed"}"#,
+            ),
+            (
+                ConfigError::PromptFailed {
+                    name: named("foo"),
+                    source: io::Error::other("no tty"),
+                },
+                r#"{"kind":"PromptFailed","code":17,"option":"foo","message":"failed to read value for option 'foo': no tty"}"#,
+            ),
+            (
+                ConfigError::InvalidMetadata { field: "version" },
+                r#"{"kind":"InvalidMetadata","code":18,"option":null,"message":"the version of the configuration must not be empty"}"#,
+            ),
+            (
+                ConfigError::DanglingReference {
+                    from: "keystore-password",
+                    to: "keystore",
+                },
+                r#"{"kind":"DanglingReference","code":19,"option":null,"message":"'keystore-password' refers to the unknown option 'keystore'"}"#,
+            ),
+            (
+                ConfigError::GroupNameClash { name: "mode" },
+                r#"{"kind":"GroupNameClash","code":27,"option":null,"message":"the group 'mode' has the same name as an option"}"#,
+            ),
+            (
+                ConfigError::InvalidArguments(clap::Error::with_description(
+                    "Found argument '--bar'",
+                    clap::ErrorKind::UnknownArgument,
+                )),
+                r#"{"kind":"InvalidArguments","code":20,"option":null,"message":"error: Found argument '--bar'"}"#,
+            ),
+            (
+                ConfigError::ParseValues(anyhow::anyhow!("invalid \"port\"\n\tvalue")),
+                r#"{"kind":"ParseValues","code":21,"option":null,"message":"invalid \"port\"\n\tvalue"}"#,
+            ),
+        ]
+    }
+
+    #[test]
+    fn to_json() {
+        for (error, expected) in cases() {
+            assert_eq!(error.to_json(), expected);
+        }
+    }
+
+    #[test]
+    fn codes() {
+        let missing = ConfigError::MissingRequired {
+            name: String::from("foo"),
+        };
+        assert_eq!(missing.code(), 1);
+        assert_eq!(
+            format!("{:#}", missing),
+            "E0001: required option 'foo' was not provided"
+        );
+        assert_eq!(
+            missing.to_string(),
+            "required option 'foo' was not provided"
+        );
+
+        let not_found = ConfigError::ConfigFileNotFound {
+            path: PathBuf::from("/etc/tool.conf"),
+            source: io::Error::new(io::ErrorKind::NotFound, "not found"),
+        };
+        assert_eq!(not_found.code(), 13);
+        assert!(format!("{:#}", not_found).starts_with("E0013: failed to open config file"));
+
+        // Every kind has a code of its own, which is part of the alternate form used for logging
+        let mut codes = vec![];
+        for (error, _) in cases() {
+            assert_eq!(
+                format!("{:#}", error),
+                format!("E{:04}: {}", error.code(), error),
+                "{}",
+                error.kind()
+            );
+            codes.push(error.code());
+        }
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes, (1..=27).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,135 @@
+//! This module reads values that are streamed through a named pipe (FIFO), as used by some
+//! secret delivery schemes.
+//! A plain read blocks until a writer shows up, which can hang the start of a tool forever,
+//! so the pipe is read without blocking and with a deadline instead.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Whether the path points to a named pipe
+pub(crate) fn is_fifo(path: &Path) -> bool {
+    path.metadata()
+        .map(|metadata| metadata.file_type().is_fifo())
+        .unwrap_or(false)
+}
+
+/// Reads everything a writer sends through the pipe until it closes its end
+///
+/// Returns an error of kind `TimedOut` if the writer did not finish within the timeout.
+pub(crate) fn read_to_string(path: &Path, timeout: Duration) -> io::Result<String> {
+    // Opening for reading without O_NONBLOCK would already block until there is a writer
+    let mut file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)?;
+    let deadline = Instant::now() + timeout;
+
+    let mut content = vec![];
+    let mut buffer = [0; 4096];
+    loop {
+        wait_readable(&file, deadline)?;
+        match file.read(&mut buffer) {
+            // The writer closed the pipe
+            Ok(0) => break,
+            Ok(read) => content.extend_from_slice(&buffer[..read]),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    String::from_utf8(content).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+// Waits until there is data to read or the writer hung up
+// Until a writer opened the pipe for the first time neither is reported
+fn wait_readable(file: &File, deadline: Instant) -> io::Result<()> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(timed_out());
+        }
+        let mut fds = libc::pollfd {
+            fd: file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = remaining.as_millis().clamp(1, libc::c_int::MAX as u128) as libc::c_int;
+        // SAFETY: fds is a single valid pollfd for the duration of the call
+        match unsafe { libc::poll(&mut fds, 1, timeout_ms) } {
+            0 => return Err(timed_out()),
+            ready if ready > 0 => return Ok(()),
+            _ => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        "no value was written to the pipe in time",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_fifo, read_to_string};
+    use std::ffi::CString;
+    use std::fs;
+    use std::io::{self, Write};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::thread;
+    use std::time::Duration;
+
+    fn create_fifo(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("stackable-config-{}", name));
+        let _ = fs::remove_file(&path);
+        let c_path = CString::new(path.as_os_str().as_bytes()).expect("Path contains NUL");
+        // SAFETY: c_path is a valid NUL terminated string
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+        path
+    }
+
+    #[test]
+    fn read_from_writer() {
+        let path = create_fifo("fifo-read");
+        assert!(is_fifo(&path));
+
+        let writer_path = path.clone();
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            let mut pipe = fs::OpenOptions::new()
+                .write(true)
+                .open(writer_path)
+                .expect("Error opening pipe");
+            pipe.write_all(b"changeit\n").expect("Error writing pipe");
+        });
+
+        let value = read_to_string(&path, Duration::from_secs(10)).expect("Error reading pipe");
+        writer.join().expect("Writer failed");
+        assert_eq!(value, "changeit\n");
+        fs::remove_file(path).expect("Error removing pipe");
+    }
+
+    #[test]
+    fn timeout_without_writer() {
+        let path = create_fifo("fifo-timeout");
+        let result = read_to_string(&path, Duration::from_millis(100));
+        assert_eq!(
+            result.map_err(|err| err.kind()),
+            Err(io::ErrorKind::TimedOut)
+        );
+        fs::remove_file(path).expect("Error removing pipe");
+
+        assert!(!is_fifo(Path::new(env!("CARGO_MANIFEST_DIR"))));
+    }
+}
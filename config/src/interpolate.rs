@@ -0,0 +1,123 @@
+//! This module resolves references to the values of other options within the config file,
+//! e.g. `--log-dir=${base-dir}/logs`.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+use crate::ConfigError;
+
+/// Replaces every `${name}` in the values of `--name=value` arguments by the value of the
+/// option `name` as it was last set by an earlier argument
+///
+/// The arguments are processed in order, so a value can only refer to options that were set
+/// before it, and since references are replaced right away chained references resolve too.
+/// `$${` is written as a literal `${`. Arguments that are not valid UTF-8 or don't have the
+/// form `--name=value` are kept as they are.
+pub fn interpolate(args: Vec<OsString>) -> Result<Vec<OsString>, ConfigError> {
+    let mut defined: HashMap<String, String> = HashMap::new();
+    let mut interpolated = Vec::with_capacity(args.len());
+    for arg in args {
+        let (name, value) = match arg.to_str().and_then(split_argument) {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None => {
+                interpolated.push(arg);
+                continue;
+            }
+        };
+        let value = expand(&name, &value, &defined)?;
+        interpolated.push(OsString::from(format!("--{}={}", name, value)));
+        defined.insert(name, value);
+    }
+    Ok(interpolated)
+}
+
+// Splits `--name=value` into name and value
+fn split_argument(arg: &str) -> Option<(&str, &str)> {
+    let (name, value) = arg.strip_prefix("--")?.split_once('=')?;
+    if name.is_empty() {
+        None
+    } else {
+        Some((name, value))
+    }
+}
+
+fn expand(
+    name: &str,
+    value: &str,
+    defined: &HashMap<String, String>,
+) -> Result<String, ConfigError> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            expanded.push_str(&rest[..start - 1]);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        expanded.push_str(&rest[..start]);
+
+        let undefined = |reference: &str| ConfigError::UndefinedReference {
+            name: name.to_string(),
+            reference: reference.to_string(),
+        };
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| undefined(&rest[start..]))?;
+        let reference = &rest[start + 2..start + end];
+        let referenced = defined.get(reference).ok_or_else(|| undefined(reference))?;
+        expanded.push_str(referenced);
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::interpolate;
+    use crate::ConfigError;
+    use std::ffi::OsString;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn backward_reference() {
+        assert_eq!(
+            interpolate(args(&["--base-dir=/data", "--log-dir=${base-dir}/logs"]))
+                .expect("Error interpolating"),
+            args(&["--base-dir=/data", "--log-dir=/data/logs"])
+        );
+    }
+
+    #[test]
+    fn chained_reference() {
+        assert_eq!(
+            interpolate(args(&[
+                "--root=/srv",
+                "--base-dir=${root}/data",
+                "--switch",
+                "--log-dir=${base-dir}/logs/$${literal}",
+            ]))
+            .expect("Error interpolating"),
+            args(&[
+                "--root=/srv",
+                "--base-dir=/srv/data",
+                "--switch",
+                "--log-dir=/srv/data/logs/${literal}",
+            ])
+        );
+    }
+
+    #[test]
+    fn forward_reference() {
+        let result = interpolate(args(&["--log-dir=${base-dir}/logs", "--base-dir=/data"]));
+        assert!(matches!(
+            result,
+            Err(ConfigError::UndefinedReference { name, reference })
+                if name == "log-dir" && reference == "base-dir"
+        ));
+    }
+}
@@ -0,0 +1,298 @@
+//! This module turns a JSON object that maps option names to values into arguments, e.g.
+//! `{"log-dir": "/var/log", "tag": ["a", "b"], "verbose": true}`.
+
+use std::ffi::OsString;
+
+// A parsed JSON value, numbers are kept as they were written
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// Turns a JSON object into arguments in the order of its keys
+///
+/// Strings, numbers and booleans become `--name=value`, so booleans for switches are handled
+/// like `--switch=true` in the config file. Every element of an array becomes an argument of
+/// its own, an empty array is `--name=`, which clears a `list` option. `null` is ignored.
+pub(crate) fn args(content: &str) -> Result<Vec<OsString>, String> {
+    let mut parser = Parser {
+        content: content.as_bytes(),
+        position: 0,
+    };
+    let entries = match parser.document()? {
+        Json::Object(entries) => entries,
+        _ => return Err(String::from("the value is not a JSON object")),
+    };
+
+    let mut args = vec![];
+    for (name, value) in entries {
+        let values = match value {
+            Json::Null => continue,
+            Json::Array(values) if values.is_empty() => vec![String::new()],
+            Json::Array(values) => values
+                .into_iter()
+                .map(|value| scalar(&name, value))
+                .collect::<Result<_, _>>()?,
+            value => vec![scalar(&name, value)?],
+        };
+        args.extend(
+            values
+                .into_iter()
+                .map(|value| OsString::from(format!("--{}={}", name, value))),
+        );
+    }
+    Ok(args)
+}
+
+fn scalar(name: &str, value: Json) -> Result<String, String> {
+    match value {
+        Json::Bool(value) => Ok(value.to_string()),
+        Json::Number(value) | Json::String(value) => Ok(value),
+        _ => Err(format!(
+            "the value of '{}' has to be a string, number, boolean or an array of these",
+            name
+        )),
+    }
+}
+
+struct Parser<'a> {
+    content: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn document(&mut self) -> Result<Json, String> {
+        let value = self.value()?;
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(value),
+            Some(_) => Err(self.unexpected("the end of the value")),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            _ => Err(self.unexpected("a value")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.position += 1;
+        let mut entries = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.unexpected("a key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(Json::Object(entries));
+                }
+                _ => return Err(self.unexpected("',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.position += 1;
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err(self.unexpected("',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.position += 1;
+        let mut bytes = vec![];
+        loop {
+            match self.next() {
+                Some(b'"') => break,
+                Some(b'\\') => match self.next() {
+                    Some(b'"') => bytes.push(b'"'),
+                    Some(b'\\') => bytes.push(b'\\'),
+                    Some(b'/') => bytes.push(b'/'),
+                    Some(b'b') => bytes.push(0x08),
+                    Some(b'f') => bytes.push(0x0C),
+                    Some(b'n') => bytes.push(b'\n'),
+                    Some(b'r') => bytes.push(b'\r'),
+                    Some(b't') => bytes.push(b'\t'),
+                    Some(b'u') => {
+                        let c = self.unicode_escape()?;
+                        bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    }
+                    _ => return Err(self.unexpected("an escape sequence")),
+                },
+                Some(byte) if byte < 0x20 => return Err(self.unexpected("a character")),
+                Some(byte) => bytes.push(byte),
+                None => return Err(self.unexpected("'\"'")),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| String::from("a string is not valid UTF-8"))
+    }
+
+    // The part after `\u`, surrogate pairs are combined into one character
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if self.next() != Some(b'\\') || self.next() != Some(b'u') {
+                return Err(self.unexpected("a low surrogate"));
+            }
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.unexpected("a low surrogate"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.unexpected("a valid character"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self
+            .content
+            .get(self.position..self.position + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.unexpected("four hex digits"))?;
+        self.position += 4;
+        Ok(digits)
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        while let Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') = self.peek() {
+            self.position += 1;
+        }
+        let number = String::from_utf8_lossy(&self.content[start..self.position]).to_string();
+        if number.parse::<f64>().is_err() {
+            self.position = start;
+            return Err(self.unexpected("a number"));
+        }
+        Ok(Json::Number(number))
+    }
+
+    fn literal(&mut self, literal: &str, value: Json) -> Result<Json, String> {
+        if self.content[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(value)
+        } else {
+            Err(self.unexpected("a value"))
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", byte as char)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.content.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek();
+        self.position += 1;
+        byte
+    }
+
+    fn unexpected(&self, expected: &str) -> String {
+        format!("expected {} at offset {}", expected, self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::args;
+    use std::ffi::OsString;
+
+    #[test]
+    fn object_to_args() {
+        let content = r#" {
+            "log-dir": "/var/log/\"tool\"ä",
+            "tag": ["a", 1, false],
+            "clear": [],
+            "verbose": true,
+            "port": -8.5e1,
+            "unset": null
+        } "#;
+        assert_eq!(
+            args(content).expect("Error parsing JSON"),
+            vec![
+                OsString::from("--log-dir=/var/log/\"tool\"ä"),
+                OsString::from("--tag=a"),
+                OsString::from("--tag=1"),
+                OsString::from("--tag=false"),
+                OsString::from("--clear="),
+                OsString::from("--verbose=true"),
+                OsString::from("--port=-8.5e1"),
+            ]
+        );
+        assert_eq!(
+            args("{}").expect("Error parsing JSON"),
+            Vec::<OsString>::new()
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            args(r#"{"a": "b",}"#),
+            Err(String::from("expected a key at offset 10"))
+        );
+        assert!(args(r#"["a"]"#).is_err());
+        assert!(args(r#"{"a": {"b": "c"}}"#).is_err());
+        assert!(args(r#"{"a": [["b"]]}"#).is_err());
+        assert!(args(r#"{"a": "b"} x"#).is_err());
+        assert!(args(r#"{"a": "b"#).is_err());
+        assert!(args(r#"{"a": tru}"#).is_err());
+    }
+}
@@ -5,8 +5,8 @@
 //! and roughly works as follows:
 //! * Options can be specified on the command line
 //! * If an environment variable is passed and the value of that variable contains a filename,
-//! this file will be parsed as if the content had been specified as command line arguments.
-//! Arguments on the command line will take precedence over those loaded from a file.
+//!   this file will be parsed as if the content had been specified as command line arguments.
+//!   Arguments on the command line will take precedence over those loaded from a file.
 //!
 //! Interaction with this module will be using ConfigDescription and Configuration
 //! structs to define the configuration a binary/module needs and then calling get_matcher
@@ -14,14 +14,68 @@
 //!
 use std::ffi::OsString;
 
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, ArgGroup, ErrorKind};
+use log::{error, warn};
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
+use std::env;
 use std::hash::{Hash, Hasher};
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+pub use clap::Shell;
+
+pub use crate::diff::ConfigDiff;
+pub use crate::encoding::ConfigFileEncoding;
+pub use crate::error::{ConfigError, ConfigWarning};
+pub use crate::lint::ConfigLint;
+pub use crate::net::AddressKind;
+pub use crate::path::PathKind;
+use crate::prompt::{Prompt, TerminalPrompt};
+pub use crate::reload::{changed_options, ConfigReloader};
+pub use crate::ripgrep_config::parse_config_reader;
+pub use crate::url::Url;
+pub use crate::values::ResolvedValues;
+
+mod base64;
+mod config_dir;
+mod diff;
+mod doc;
+mod encoding;
+mod error;
+#[cfg(unix)]
+mod fifo;
+mod interpolate;
+mod json;
+mod lint;
+mod net;
+mod number;
+mod path;
+mod prompt;
+mod reload;
+mod url;
+mod values;
 // Include all "stolen" ripgrep code in this module
 mod ripgrep_config;
 
+// Reserved flag that makes `build` print the resolved configuration instead of returning it
+const PRINT_CONFIG: &str = "print-config";
+// Reserved flag that needs to be specified to be allowed to use experimental options
+const ENABLE_EXPERIMENTAL: &str = "enable-experimental";
+// Reserved flag that makes `build` ignore the config file
+const NO_CONFIG: &str = "no-config";
+const DEFAULT_FIFO_TIMEOUT: Duration = Duration::from_secs(10);
+// All flags that are added by this crate or by clap itself
+const RESERVED_FLAGS: &[&str] = &[
+    PRINT_CONFIG,
+    ENABLE_EXPERIMENTAL,
+    NO_CONFIG,
+    "help",
+    "version",
+];
+
 /// This trait defines the behavior that all configuration classes need to
 /// provide in order for the clap matcher to be generated from the config object
 pub trait Configurable: Sized {
@@ -34,26 +88,31 @@ pub trait Configurable: Sized {
     /// and create a meaningful representation of the data contained in there that
     /// users can then interact with
     ///
-    /// * `parsed_values` The values that were parsed from the command line arguments
-    /// The keys in the HashMap will be all ConfigOptions that were returned in the
-    /// get_config_description() call.
+    /// * `parsed_values` The values that were parsed from the command line arguments, see
+    ///   `ResolvedValues` for how to interpret them
     ///
-    /// The value in the HashMap can have three meanings:
-    /// - None: this parameter was not specified on the command line
-    /// - Some(Vec<String>) with an empty Vector: this is a boolean parameter
-    ///   and it was present on the command line
-    /// - Some(Vec<String>) with one or more list elements: parameter that takes
-    ///   a value and one or more values were specified
+    /// For options with `list` set to true the values are guaranteed to be in the order in
+    /// which they were specified, values from the config file come before values from the
+    /// command line.
     fn parse_values(
         parsed_values: HashMap<ConfigOption, Option<Vec<String>>>,
     ) -> Result<Self, anyhow::Error>;
+
+    /// This method is called by ConfigBuilder with all resolved values, by default it passes
+    /// the HashMap of the values on to `parse_values`
+    ///
+    /// Override it to use what only `ResolvedValues` offers, e.g. `get_os_path` for values
+    /// that are not valid UTF-8 or `trailing_args`.
+    fn parse_resolved(values: ResolvedValues) -> Result<Self, anyhow::Error> {
+        Self::parse_values(values.into_inner())
+    }
 }
 
 /// This struct describes some properties that can be set for an application as well
 /// as the list of options that the program can understand
 /// These values (apart from the list of options) are only used to generate the
 /// console help message
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct Configuration {
     /// The name of the application
     pub name: &'static str,
@@ -64,16 +123,181 @@ pub struct Configuration {
     /// The set of all possible command line options
     /// this is a set instead of a list as we do not want or need duplicates
     pub options: HashSet<ConfigOption>,
+    /// If true, the user will be asked for the values of all required options that were not
+    /// provided, as long as stdin is connected to a terminal
+    /// Input for options that are marked as `secret` will not be echoed
+    /// If stdin is not a terminal a `ConfigError::MissingRequired` is returned as usual
+    pub interactive: bool,
+    /// The encoding of the config file, defaults to UTF-8
+    /// If the file can't be decoded an error is logged and it is ignored, just like a file
+    /// that can't be read
+    pub config_file_encoding: ConfigFileEncoding,
+    /// If true, the config file must not be accessible by group or others, otherwise
+    /// `ConfigError::InsecureConfigPermissions` is returned
+    /// Use this if the config file can contain secrets, this is only checked on Unix
+    pub private_config_file: bool,
+    /// If true, a config file that was specified in the environment variable but can't be
+    /// read is an error (`ConfigError::ConfigFileNotFound`) instead of being logged and
+    /// ignored
+    /// An unset or empty environment variable is never an error
+    pub strict_config_file: bool,
+    /// The name of an environment variable that can point to a directory with one file per
+    /// option, e.g. a mounted Kubernetes ConfigMap or Secret
+    /// Every file is read as `--<filename>=<content>`, these values override those from the
+    /// config file but not those from the command line
+    /// Nested directories and hidden files are ignored
+    pub config_dir_env: Option<&'static str>,
+    /// The directory that relative values of `absolute_path` options are resolved against,
+    /// the current working directory is used if this is not set
+    pub base_dir: Option<PathBuf>,
+    /// Whether the help output uses colors, defaults to `ColorChoice::Auto`
+    pub color: ColorChoice,
+    /// Defaults in the format of the config file, usually compiled into the tool with
+    /// `include_str!`, these have the lowest precedence of all sources
+    pub defaults: &'static str,
+    /// If set, every option can also be specified as an environment variable named
+    /// `<PREFIX>_<NAME>`, with the name in upper case and `-` replaced by `_`, e.g.
+    /// `MYTOOL_LOG_DIR` for the option `log-dir`
+    /// These values take precedence over the config file but not over the command line
+    pub env_prefix: Option<&'static str>,
+    /// If set, this environment variable can contain a JSON object that maps option names
+    /// to values, e.g. `{"log-dir": "/var/log", "tag": ["a", "b"], "verbose": true}`
+    /// Arrays are the values of `list` options and booleans turn switches on or off, these
+    /// values take precedence over the config file but not over the variables of
+    /// `env_prefix` or the command line
+    /// A value that is not such an object is `ConfigError::InvalidConfigContent`
+    pub json_env: Option<&'static str>,
+    /// How long to wait for a value that is read from a named pipe with `@<file>`, defaults
+    /// to 10 seconds
+    /// If nothing was written in time `ConfigError::SecretSourceTimeout` is returned, so a
+    /// hung secret source does not block the start of the tool forever
+    pub fifo_timeout: Option<Duration>,
+    /// Makes unknown arguments a hard error in every mode, including `build_partial` which
+    /// would otherwise collect them
+    /// Tools that are not meant to be extended by plugins can set this so an unknown
+    /// argument is never silently passed on
+    pub strict_args: bool,
+    /// Groups of options of which exactly one has to be specified, e.g. different modes
+    /// like `--stdin`, `--file` and `--url`
+    /// Specifying none or more than one of them is `ConfigError::InvalidArguments`, this
+    /// includes values from the config file
+    pub required_one_of: Vec<OptionGroup>,
+    /// If true, everything after the first `--` on the command line is passed through
+    /// untouched instead of being parsed, e.g. the command line of a program that the tool
+    /// wraps, see `ResolvedValues::trailing_args`
+    pub trailing_args: bool,
+    /// If true, values in the config file can refer to the values of options that were set
+    /// before them in the same file, e.g. `--log-dir=${base-dir}/logs`, `$${` is a literal `${`
+    /// Only values written as `--option=value` are considered, referring to an option that is
+    /// not set before is `ConfigError::UndefinedReference`
+    pub interpolate_config_file: bool,
+    /// A file in the format of the config file with defaults that packagers can edit, e.g.
+    /// `/etc/mytool/defaults.conf`
+    /// Its values take precedence over `defaults` and the `default` of the options, but not
+    /// over any other source, the file is silently ignored if it doesn't exist
+    pub defaults_file: Option<PathBuf>,
+    /// Additional information for tooling as key value pairs, e.g. `("docs", "https://...")`
+    /// It isn't used when parsing, but is included in the generated Markdown and JSON Schema
+    pub metadata: &'static [(&'static str, &'static str)],
+    /// If true, specifying an option that is not a `list` option more than once on the
+    /// command line is `ConfigError::DuplicateFlag` instead of the last value winning
+    /// Values from the config file can still be overridden on the command line
+    pub reject_duplicate_flags: bool,
+}
+
+impl Configuration {
+    /// All options that are marked as `required`, sorted by name
+    pub fn required_options(&self) -> Vec<&ConfigOption> {
+        self.sorted_options(|option| option.required)
+    }
+
+    /// All options that are not marked as `required`, sorted by name
+    pub fn optional_options(&self) -> Vec<&ConfigOption> {
+        self.sorted_options(|option| !option.required)
+    }
+
+    // Replaces the `{name}` and `{version}` placeholders in the default value of an option
+    pub(crate) fn expand_default(&self, default: &str) -> String {
+        default
+            .replace("{name}", self.name)
+            .replace("{version}", self.version)
+    }
+
+    pub(crate) fn sorted_options(
+        &self,
+        filter: impl Fn(&ConfigOption) -> bool,
+    ) -> Vec<&ConfigOption> {
+        let mut options: Vec<&ConfigOption> = self
+            .options
+            .iter()
+            .filter(|option| filter(option))
+            .collect();
+        options.sort();
+        options
+    }
+}
+
+/// When to use colors in the generated help output
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ColorChoice {
+    /// Use colors if the output is a terminal and the `NO_COLOR` environment variable is not
+    /// set (or empty)
+    #[default]
+    Auto,
+    /// Always use colors
+    Always,
+    /// Never use colors
+    Never,
+}
+
+impl ColorChoice {
+    fn settings(&self) -> &'static [AppSettings] {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        match self {
+            ColorChoice::Auto if no_color => &[AppSettings::ColorNever],
+            ColorChoice::Auto => &[AppSettings::ColoredHelp, AppSettings::ColorAuto],
+            ColorChoice::Always => &[AppSettings::ColoredHelp, AppSettings::ColorAlways],
+            ColorChoice::Never => &[AppSettings::ColorNever],
+        }
+    }
+}
+
+/// Options that belong together, see `Configuration::required_one_of`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OptionGroup {
+    /// The name of the group, this must not be the name of an option
+    pub name: &'static str,
+    /// The names of the options in this group
+    pub options: &'static [&'static str],
+}
+
+/// The result of building a configuration with `ConfigBuilder::build_safe`, which
+/// never exits the process by itself
+#[derive(Debug)]
+pub enum ConfigOutcome<T> {
+    /// The configuration was built and the tool should run with it
+    Config(T),
+    /// `--print-config` was specified, the tool should print the contained resolved
+    /// configuration and exit successfully without doing anything else
+    PrintConfig(String),
+    /// `--help` (or `-h`) was specified, the tool should print the contained help text and
+    /// exit successfully
+    HelpRequested(String),
+    /// `--version` (or `-V`) was specified, the tool should print the contained name and
+    /// version and exit successfully
+    VersionRequested(String),
 }
 
 /// Represents an individual config option that the program can interpret
-#[derive(Clone, Debug, Eq, Default)]
+#[derive(Clone, Debug, Eq)]
 pub struct ConfigOption {
     /// The name of the option (without leading --)
     pub name: &'static str,
     /// Default value to use for the option if it is not provided
     /// NOTE: this will be ignored if *takes_argument* is true, as
     /// a default value for a switch does not make too much sense
+    /// The placeholders `{name}` and `{version}` are replaced by the name and version of the
+    /// `Configuration`, so shared option definitions can use e.g. `/var/lib/{name}/data`
     pub default: Option<&'static str>,
     /// Whether this option has to be provided
     pub required: bool,
@@ -84,10 +308,136 @@ pub struct ConfigOption {
     pub help: &'static str,
     /// Longer text to use when generating documentation/website/...
     pub documentation: &'static str,
+    /// An example value, e.g. `30s`, it is appended to the help text as `(e.g. 30s)` and
+    /// included in the generated documentation
+    pub example: Option<&'static str>,
     /// Allow specifying this argument multiple times?
     /// If true, multiple occurrences of this argument will all be taken into account, if false
     /// only the last occurence will be used, any previous values will be overwritten
+    /// The values of a list option are returned in the order in which they were specified
+    /// (first occurrence first), with values from the config file preceding those from the
+    /// command line
+    /// An empty value (e.g. `--option=`) clears all values specified before it, this allows
+    /// discarding the values from the config file on the command line. If nothing follows the
+    /// empty value the option is treated as not set.
     pub list: bool,
+    /// Marks the value of this option as sensitive (e.g. a password), it will not be echoed
+    /// when prompting for it
+    pub secret: bool,
+    /// If set, this option is deprecated and a warning is logged whenever it is used
+    /// The text should tell users what to do instead, e.g. "use 'bar'"
+    pub deprecated: Option<&'static str>,
+    /// The version in which this option was deprecated, only used if `deprecated` is set
+    pub deprecated_since: Option<&'static str>,
+    /// Marks this option as experimental, it may only be used if `--enable-experimental` is
+    /// specified as well (on the command line or in the config file)
+    pub experimental: bool,
+    /// Marks the value of this option as a filesystem path, the value is kept as is and
+    /// does not need to be valid UTF-8, see `ResolvedValues::get_os_path`
+    pub path: bool,
+    /// If set, every value of this option has to be a path that matches this expectation
+    /// Options with a `path_kind` are always treated as `path` options
+    pub path_kind: Option<PathKind>,
+    /// Names of other options that have to be specified as well whenever this option is
+    /// specified, e.g. a password that is needed to open a keystore
+    pub requires: &'static [&'static str],
+    /// If true, relative paths given as values of this option are turned into absolute ones
+    /// by joining them to `Configuration::base_dir`
+    /// This does not access the filesystem, so `..` and symlinks are kept as they are
+    /// Options with `absolute_path` set are always treated as `path` options
+    pub absolute_path: bool,
+    /// If true, duplicate values of a `list` option are removed, only the first occurrence of
+    /// every value is kept, e.g. when the config file and the command line both add the same
+    /// value
+    pub dedup_list: bool,
+    /// If set, every value of this option has to be a network address of this kind, this is
+    /// checked while parsing the arguments, see `ResolvedValues::get_socket_addr`
+    pub address: Option<AddressKind>,
+    /// If not empty, every value of this option has to be one of these, this is checked while
+    /// parsing the arguments, see `ResolvedValues::get_enum`
+    pub possible_values: &'static [&'static str],
+    /// Marks the value of this option as binary data encoded as base64, it is checked while
+    /// parsing the arguments, see `ResolvedValues::get_bytes_b64`
+    pub base64: bool,
+    /// If true, surrounding whitespace is removed from every value of this option and a value
+    /// that is empty afterwards is rejected with `ConfigError::EmptyValue`
+    pub non_empty: bool,
+    /// If not empty, every value of this option has to be a URL with one of these schemes,
+    /// e.g. `&["https"]`, this is checked while parsing the arguments, see
+    /// `ResolvedValues::get_url`
+    pub url_schemes: &'static [&'static str],
+    /// If true, a value of the form `@<file>` of this `list` option is replaced by all entries
+    /// in that file instead of its whole content, e.g. for long allowlists
+    /// Entries are separated by newlines or commas, everything after a `#` on a line is a
+    /// comment and empty entries are ignored
+    pub list_from_file: bool,
+}
+
+impl ConfigOption {
+    /// An option with all fields empty or switched off
+    /// This can be used to only specify the relevant fields when defining options in a
+    /// const context: `ConfigOption { name: "foo", ..ConfigOption::DEFAULT }`
+    pub const DEFAULT: ConfigOption = ConfigOption {
+        name: "",
+        default: None,
+        required: false,
+        takes_argument: false,
+        help: "",
+        documentation: "",
+        example: None,
+        list: false,
+        secret: false,
+        deprecated: None,
+        deprecated_since: None,
+        experimental: false,
+        path: false,
+        path_kind: None,
+        requires: &[],
+        absolute_path: false,
+        dedup_list: false,
+        address: None,
+        possible_values: &[],
+        base64: false,
+        non_empty: false,
+        url_schemes: &[],
+        list_from_file: false,
+    };
+
+    /// Whether values of this option are paths, i.e. `path`, `path_kind` or `absolute_path`
+    /// is set
+    pub fn is_path(&self) -> bool {
+        self.path || self.path_kind.is_some() || self.absolute_path
+    }
+
+    /// The help text as shown by `--help`, with the `example` appended if there is one
+    /// e.g. `Request timeout (e.g. 30s)`
+    pub fn help_with_example(&self) -> String {
+        match self.example {
+            Some(example) if self.help.is_empty() => format!("(e.g. {})", example),
+            Some(example) => format!("{} (e.g. {})", self.help, example),
+            None => self.help.to_string(),
+        }
+    }
+
+    /// The warning to show when this option is used, `None` if the option is not deprecated
+    /// e.g. `'foo' deprecated since 1.2.0; use 'bar'`
+    pub fn deprecation_warning(&self) -> Option<String> {
+        let hint = self.deprecated?;
+        let mut warning = format!("'{}' deprecated", self.name);
+        if let Some(since) = self.deprecated_since {
+            warning.push_str(&format!(" since {}", since));
+        }
+        if !hint.is_empty() {
+            warning.push_str(&format!("; {}", hint));
+        }
+        Some(warning)
+    }
+}
+
+impl Default for ConfigOption {
+    fn default() -> Self {
+        ConfigOption::DEFAULT
+    }
 }
 
 // Necessary to be able to use a ConfigOption as key in a HashMap
@@ -124,22 +474,59 @@ impl Hash for ConfigOption {
     }
 }
 
+// Everything that was resolved from the command line and config file by a single run of
+// `ConfigBuilder::resolve`
+struct Resolved {
+    // The values for all options as they are passed to `Configurable::parse_resolved`
+    values: ResolvedValues,
+    // Arguments that did not belong to any option, only collected in partial mode
+    unknown: Vec<OsString>,
+    // Arguments for all values the user was prompted for
+    prompted: Vec<OsString>,
+    // Whether --print-config was specified
+    print_config: bool,
+}
+
+// Where the arguments from the config file come from
+#[derive(Clone, Copy)]
+enum ConfigSource<'a> {
+    // The path of the config file is read from this environment variable
+    Env(&'a str),
+    // The config was already parsed into arguments, see `ConfigBuilder::build_from_reader`
+    Args(&'a [OsString]),
+}
+
+// What was read from the config files while combining the arguments
+#[derive(Default)]
+struct FilesRead {
+    // The config file the values were read from, if any
+    config_file: Option<PathBuf>,
+    // Lines of the config files that were skipped
+    warnings: Vec<ConfigWarning>,
+}
+
 /// A struct that provides associated functions to generate a Clap matcher from a configuration
 /// that is described by a struct implementing the Configurable trait.
 ///
 /// The general flow is like this:
 /// 1. ConfigBuilder calls the associated function get_config_description on the
-/// config object to retrieve the description of the configuration
+///    config object to retrieve the description of the configuration
 /// 2. Creates a matcher based on the ConfigOptions from that object
-/// 3. Use matcher to parse command line arguments
-/// 4. If --no-config parameter was specified return parsed config
+/// 3. Look for --no-config on the command line, without validating any other arguments yet
+/// 4. If --no-config parameter was specified only use the defaults, environment variables
+///    and the command line arguments
 /// 5. If --no-config is not present check environment variable STACKABLE_CONFIG_PATH
 ///    if an external config file is specified
-/// 6. Parse config from file and prepend all options to the command line arguments
-/// 7. Re-parse combined arguments
-/// 8. Call associated function parse_values on config object to create a config object
-/// that is populated with proper values based on the parsed argument
-/// 9. Return the populated config object
+/// 6. Prepend the defaults, the options from the config file (and directory) and those from
+///    environment variables to the command line arguments, in this order
+/// 7. Re-parse combined arguments, if required options are missing and the configuration
+///    is interactive, prompt for them and parse again
+/// 8. If --print-config was specified, render the resolved values and signal that they should
+///    be printed instead of running the tool
+/// 9. Call associated function parse_resolved (by default parse_values) on config object to
+///    create a config object
+///    that is populated with proper values based on the parsed argument
+/// 10. Return the populated config object
 ///
 /// This effectively means that config can be either provided on the command line, or
 /// in a file that is specified via environment variable, with options from the command
@@ -154,61 +541,869 @@ impl ConfigBuilder {
     /// pass back the parsed values of those parameters.
     ///
     /// * `commandline` The command line parameters to parse the configuration from (first element will be
-    /// ignored, as this is the binary name
+    ///   ignored, as this is the binary name
     /// * `config_file_env` Name of the environment variable to read an extra config file from
+    ///
+    /// If `--print-config` is specified, the resolved configuration is printed and the process
+    /// exits, the same way as for `--help`, use `build_safe` to handle this yourself.
     pub fn build<T: Configurable>(
         commandline: Vec<OsString>,
         config_file_env: &str,
-    ) -> Result<T, anyhow::Error> {
+    ) -> Result<T, ConfigError> {
+        ConfigBuilder::build_with_prompt(commandline, config_file_env, &mut TerminalPrompt, false)
+            .map(|(outcome, _)| ConfigBuilder::exit_unless_config(outcome))
+    }
+
+    /// Works like `build` but instead of exiting the process when `--print-config` is
+    /// specified, this is returned as a `ConfigOutcome::PrintConfig` containing the rendered
+    /// configuration with all secrets masked, the same goes for `--help` and `--version`
+    /// which are returned as `ConfigOutcome::HelpRequested` and `ConfigOutcome::VersionRequested`
+    ///
+    /// * `commandline` The command line parameters to parse the configuration from (first element will be
+    ///   ignored, as this is the binary name
+    /// * `config_file_env` Name of the environment variable to read an extra config file from
+    pub fn build_safe<T: Configurable>(
+        commandline: Vec<OsString>,
+        config_file_env: &str,
+    ) -> Result<ConfigOutcome<T>, ConfigError> {
+        ConfigBuilder::build_with_prompt(commandline, config_file_env, &mut TerminalPrompt, false)
+            .map(|(outcome, _)| outcome)
+    }
+
+    /// Works like `build` but instead of failing on arguments that don't belong to any of the
+    /// options of the configuration, these are collected and returned alongside the config
+    /// object, so they can be handled elsewhere (e.g. by plugins)
+    ///
+    /// The unrecognized arguments are returned untouched and in their original order, those
+    /// from the config file first. Any values following an unrecognized flag are considered
+    /// to belong to that flag and are returned as well, as is everything after a `--`.
+    /// Note that every value of a recognized `list` option needs its own flag in this mode.
+    ///
+    /// * `commandline` The command line parameters to parse the configuration from (first element will be
+    ///   ignored, as this is the binary name
+    /// * `config_file_env` Name of the environment variable to read an extra config file from
+    pub fn build_partial<T: Configurable>(
+        commandline: Vec<OsString>,
+        config_file_env: &str,
+    ) -> Result<(T, Vec<OsString>), ConfigError> {
+        ConfigBuilder::build_with_prompt(commandline, config_file_env, &mut TerminalPrompt, true)
+            .map(|(outcome, unknown)| (ConfigBuilder::exit_unless_config(outcome), unknown))
+    }
+
+    // Prints the resolved configuration, help or version and exits if that was requested
+    fn exit_unless_config<T>(outcome: ConfigOutcome<T>) -> T {
+        match outcome {
+            ConfigOutcome::Config(config) => config,
+            ConfigOutcome::PrintConfig(rendered) => {
+                print!("{}", rendered);
+                std::process::exit(0);
+            }
+            ConfigOutcome::HelpRequested(text) | ConfigOutcome::VersionRequested(text) => {
+                println!("{}", text);
+                std::process::exit(0);
+            }
+        }
+    }
+
+    // Help and version output are reported as errors by clap, these are turned into the
+    // matching outcome, all other errors are returned as they are
+    fn requested_output<T>(err: ConfigError) -> Result<ConfigOutcome<T>, ConfigError> {
+        match err {
+            ConfigError::InvalidArguments(err) if err.kind == ErrorKind::HelpDisplayed => {
+                Ok(ConfigOutcome::HelpRequested(err.message))
+            }
+            ConfigError::InvalidArguments(err) if err.kind == ErrorKind::VersionDisplayed => {
+                Ok(ConfigOutcome::VersionRequested(err.message))
+            }
+            err => Err(err),
+        }
+    }
+
+    // Prints help or version and exits if that was requested, for the builders that can't
+    // return a `ConfigOutcome`
+    fn exit_on_requested_output(err: ConfigError) -> ConfigError {
+        match ConfigBuilder::requested_output::<()>(err) {
+            Ok(outcome) => {
+                ConfigBuilder::exit_unless_config(outcome);
+                unreachable!("only help or version are requested output")
+            }
+            Err(err) => err,
+        }
+    }
+
+    fn build_with_prompt<T: Configurable>(
+        commandline: Vec<OsString>,
+        config_file_env: &str,
+        prompt: &mut dyn Prompt,
+        collect_unknown: bool,
+    ) -> Result<(ConfigOutcome<T>, Vec<OsString>), ConfigError> {
         // Parse commandline according to config definition
         let description = T::get_config_description();
+        let resolved = match ConfigBuilder::resolve(
+            &description,
+            commandline,
+            config_file_env,
+            prompt,
+            collect_unknown,
+        ) {
+            Ok(resolved) => resolved,
+            Err(err) => {
+                return ConfigBuilder::requested_output(err).map(|outcome| (outcome, vec![]))
+            }
+        };
+
+        if resolved.print_config {
+            let rendered = description.render_values(&resolved.values);
+            return Ok((ConfigOutcome::PrintConfig(rendered), resolved.unknown));
+        }
+
+        // Return an actual object of the configuration that is populated with appropriate values
+        Ok((
+            ConfigOutcome::Config(T::parse_resolved(resolved.values)?),
+            resolved.unknown,
+        ))
+    }
+
+    /// Builds the configuration like `build` and additionally returns a `ConfigReloader`
+    /// which can later be used to build it again after the config file has changed
+    ///
+    /// * `commandline` The command line parameters to parse the configuration from (first element will be
+    ///   ignored, as this is the binary name
+    /// * `config_file_env` Name of the environment variable to read an extra config file from
+    pub fn build_reloadable<T: Configurable>(
+        commandline: Vec<OsString>,
+        config_file_env: &str,
+    ) -> Result<(T, ConfigReloader), ConfigError> {
+        let description = T::get_config_description();
+        let resolved = ConfigBuilder::resolve(
+            &description,
+            commandline.clone(),
+            config_file_env,
+            &mut TerminalPrompt,
+            false,
+        )
+        .map_err(ConfigBuilder::exit_on_requested_output)?;
+
+        if resolved.print_config {
+            ConfigBuilder::exit_unless_config::<T>(ConfigOutcome::PrintConfig(
+                description.render_values(&resolved.values),
+            ));
+        }
+
+        let reloader = ConfigReloader::new(
+            commandline,
+            resolved.prompted,
+            config_file_env,
+            resolved.values.clone(),
+        );
+        Ok((T::parse_resolved(resolved.values)?, reloader))
+    }
+
+    /// Works like `build` but reads the config from the given reader instead of a file whose
+    /// path is taken from an environment variable, so the filesystem is not involved at all
+    ///
+    /// This is meant for embedding, e.g. in a test harness that holds the config in memory.
+    /// The content is parsed with `parse_config_reader` and takes the place of the config
+    /// file, so the command line still takes precedence and `--no-config` ignores it.
+    ///
+    /// * `commandline` The command line parameters to parse the configuration from (first element will be
+    ///   ignored, as this is the binary name
+    /// * `reader` The content of the config file
+    pub fn build_from_reader<T: Configurable, R: Read>(
+        commandline: Vec<OsString>,
+        reader: R,
+    ) -> Result<T, ConfigError> {
+        let config_args = parse_config_reader(reader)?;
+        let description = T::get_config_description();
+        let resolved = ConfigBuilder::resolve_from(
+            &description,
+            commandline,
+            ConfigSource::Args(&config_args),
+            &mut TerminalPrompt,
+            false,
+        )
+        .map_err(ConfigBuilder::exit_on_requested_output)?;
+
+        if resolved.print_config {
+            ConfigBuilder::exit_unless_config::<T>(ConfigOutcome::PrintConfig(
+                description.render_values(&resolved.values),
+            ));
+        }
+        Ok(T::parse_resolved(resolved.values)?)
+    }
+
+    // Resolves the values of all options from the command line and config file
+    fn resolve(
+        description: &Configuration,
+        commandline: Vec<OsString>,
+        config_file_env: &str,
+        prompt: &mut dyn Prompt,
+        collect_unknown: bool,
+    ) -> Result<Resolved, ConfigError> {
+        ConfigBuilder::resolve_from(
+            description,
+            commandline,
+            ConfigSource::Env(config_file_env),
+            prompt,
+            collect_unknown,
+        )
+    }
+
+    fn resolve_from(
+        description: &Configuration,
+        commandline: Vec<OsString>,
+        source: ConfigSource,
+        prompt: &mut dyn Prompt,
+        collect_unknown: bool,
+    ) -> Result<Resolved, ConfigError> {
+        // A broken description is a mistake of the tool, not of the user, so this fails
+        // before anything is parsed
+        description.validate()?;
 
         // Use the command line parameters defined in the description to build a
         // clap matcher object that can be used to parse the acual parameters
-        let matcher = ConfigBuilder::create_matcher(&description);
+        let help_texts = ConfigBuilder::help_texts(description);
+        let matcher = ConfigBuilder::create_matcher(description, &help_texts);
+
+        let collect_unknown = collect_unknown && !description.strict_args;
+
+        // clap prints the version itself instead of returning it like the help text, so this
+        // has to be handled before clap gets to see the arguments
+        if ConfigBuilder::flag_requested(description, &commandline, &["--version", "-V"]) {
+            return Err(ConfigError::InvalidArguments(clap::Error {
+                message: format!("{} {}", description.name, description.version),
+                kind: ErrorKind::VersionDisplayed,
+                info: None,
+            }));
+        }
+
+        // Arguments after `--` must not be looked at by anything below
+        let (commandline, trailing) = if description.trailing_args {
+            ConfigBuilder::split_trailing_arguments(commandline)
+        } else {
+            (commandline, vec![])
+        };
+
+        // Unknown arguments have to be removed before handing anything to clap
+        let (commandline, mut unknown) = if collect_unknown {
+            ConfigBuilder::split_unknown_arguments(description, commandline)
+        } else {
+            (commandline, vec![])
+        };
+
+        if description.reject_duplicate_flags {
+            if let Some(name) = ConfigBuilder::duplicate_flag(description, &commandline) {
+                return Err(ConfigError::DuplicateFlag {
+                    name: name.to_string(),
+                });
+            }
+        }
 
         // Overwrite command line arguments with final arguments to parse
         // if a config file was specified, all options from that file will be
         // prepended to the command line arguments
-        let commandline =
-            ConfigBuilder::maybe_combine_arguments(matcher.clone(), &commandline, config_file_env)?;
+        let (mut commandline, files) =
+            ConfigBuilder::maybe_combine_arguments(description, &commandline, source)?;
+
+        // The config file might contain unknown arguments as well, these come first
+        if collect_unknown {
+            let (known, mut unknown_from_file) =
+                ConfigBuilder::split_unknown_arguments(description, commandline);
+            commandline = known;
+            unknown_from_file.append(&mut unknown);
+            unknown = unknown_from_file;
+        }
 
         // Parse command line
-        let matcher = matcher.get_matches_from(commandline);
+        let mut prompted = vec![];
+        let matcher = match matcher.clone().get_matches_from_safe(commandline.clone()) {
+            Ok(matcher) => matcher,
+            Err(err) if err.kind == ErrorKind::MissingRequiredArgument => {
+                let missing = ConfigBuilder::missing_required(description, &commandline)?;
+                if !description.interactive || !prompt.is_interactive() {
+                    // Should never be empty as clap just told us something is missing
+                    return Err(match missing.first() {
+                        Some(option) => ConfigError::MissingRequired {
+                            name: option.name.to_string(),
+                        },
+                        None => ConfigBuilder::clap_error(err),
+                    });
+                }
+
+                // Ask for all missing values and append them to the arguments, this way the
+                // values go through the same validation by clap as all other values
+                for option in missing {
+                    let value =
+                        prompt
+                            .read_value(option)
+                            .map_err(|source| ConfigError::PromptFailed {
+                                name: option.name.to_string(),
+                                source,
+                            })?;
+                    prompted.push(OsString::from(format!("--{}", option.name)));
+                    prompted.push(OsString::from(value));
+                }
+                commandline.extend(prompted.iter().cloned());
+                matcher
+                    .get_matches_from_safe(commandline)
+                    .map_err(ConfigBuilder::clap_error)?
+            }
+            Err(err) => return Err(ConfigBuilder::clap_error(err)),
+        };
 
-        // Convert results from command line parsing into a HashMap<ConfigOption, Vec<String>>
+        // Convert results from command line parsing into ResolvedValues
         // this is then passed to the actual implementation of the configuration for processing
-        let mut result: HashMap<ConfigOption, Option<Vec<String>>> = HashMap::new();
+        let mut result = ResolvedValues::default();
+        result.config_file = files.config_file;
+        result.warnings = files.warnings;
+        result.trailing = trailing;
 
         for config_option in description.options.clone() {
-            if let Some(parsed_values) = matcher.values_of(config_option.name) {
-                // Convert to Vec of owned Strings, as we will want to keep these values around for
+            // Default values don't count as usage of a deprecated or experimental option
+            if matcher.occurrences_of(config_option.name) > 0 {
+                if let Some(warning) = config_option.deprecation_warning() {
+                    warn!("{}", warning);
+                }
+                if config_option.experimental && !matcher.is_present(ENABLE_EXPERIMENTAL) {
+                    return Err(ConfigError::ExperimentalNotEnabled {
+                        name: config_option.name.to_string(),
+                    });
+                }
+            }
+
+            if let (Some(default), true) = (config_option.default, config_option.takes_argument) {
+                result
+                    .defaults
+                    .insert(config_option.clone(), description.expand_default(default));
+            }
+
+            if let Some(parsed_values) = matcher.values_of_os(config_option.name) {
+                // Convert to Vec of owned values, as we will want to keep these values around for
                 // the lifetime of our application
-                let parsed_values = parsed_values.map(String::from).collect();
+                // Clap returns the values in the order in which they occurred in the arguments,
+                // which is what makes the ordering guarantee for list options hold
+                let mut parsed_values: Vec<OsString> = parsed_values.map(OsString::from).collect();
+
+                // The value is the default if the option was not specified
+                if matcher.occurrences_of(config_option.name) == 0 {
+                    parsed_values = parsed_values
+                        .iter()
+                        .map(|value| description.expand_default(&value.to_string_lossy()).into())
+                        .collect();
+                }
 
-                result.insert(config_option, Some(parsed_values));
+                // An empty value clears a list option, only what follows the last one counts
+                if config_option.list {
+                    if let Some(clear) = parsed_values.iter().rposition(|value| value.is_empty()) {
+                        parsed_values.drain(..=clear);
+                        if parsed_values.is_empty() {
+                            result.insert(config_option, None)?;
+                            continue;
+                        }
+                    }
+                }
+
+                let mut values = Vec::with_capacity(parsed_values.len());
+                for value in parsed_values {
+                    values.extend(ConfigBuilder::read_value_file(
+                        description,
+                        &config_option,
+                        value,
+                    )?);
+                }
+                parsed_values = values;
+
+                if config_option.non_empty {
+                    parsed_values = ConfigBuilder::trim_values(&config_option, parsed_values)?;
+                }
+
+                if config_option.absolute_path {
+                    parsed_values = ConfigBuilder::make_absolute(description, parsed_values)?;
+                }
+
+                if config_option.list && config_option.dedup_list {
+                    let mut seen = HashSet::new();
+                    parsed_values.retain(|value| seen.insert(value.clone()));
+                }
+
+                result.insert(config_option, Some(parsed_values))?;
             } else {
-                result.insert(config_option, None);
+                result.insert(config_option, None)?;
             }
         }
-        // Return an actual object of the configuration that is populated with appropriate values
-        T::parse_values(result)
+
+        ConfigBuilder::check_paths(description, &result)?;
+
+        Ok(Resolved {
+            values: result,
+            unknown,
+            prompted,
+            print_config: matcher.is_present(PRINT_CONFIG),
+        })
+    }
+
+    // Checks whether --no-config was specified, this only looks at the names of the arguments
+    // and skips the values of options, everything else is validated later when the arguments
+    // from the config file are known
+    fn no_config_requested(config: &Configuration, commandline: &[OsString]) -> bool {
+        ConfigBuilder::flag_requested(config, commandline, &[&format!("--{}", NO_CONFIG)])
+    }
+
+    // Checks whether one of the given flags was specified on the command line (before `--`),
+    // the same way as `no_config_requested`
+    fn flag_requested(config: &Configuration, commandline: &[OsString], flags: &[&str]) -> bool {
+        let mut value_expected = false;
+        for argument in commandline.iter().skip(1) {
+            if value_expected {
+                value_expected = false;
+                continue;
+            }
+            let argument = match argument.to_str() {
+                Some("--") => return false,
+                Some(argument) => argument,
+                None => continue,
+            };
+            if flags.contains(&argument) {
+                return true;
+            }
+            value_expected = argument
+                .strip_prefix("--")
+                .and_then(|name| config.options.iter().find(|option| option.name == name))
+                .is_some_and(|option| option.takes_argument);
+        }
+        false
+    }
+
+    // Returns the first option that is not a `list` option but was specified more than once on
+    // the command line (before `--`), values are skipped like in `flag_requested`
+    fn duplicate_flag(config: &Configuration, commandline: &[OsString]) -> Option<&'static str> {
+        let mut seen = HashSet::new();
+        let mut value_expected = false;
+        for argument in commandline.iter().skip(1) {
+            if value_expected {
+                value_expected = false;
+                continue;
+            }
+            let name = match argument.to_str() {
+                Some("--") => return None,
+                Some(argument) => match argument.strip_prefix("--") {
+                    Some(name) => name,
+                    None => continue,
+                },
+                None => continue,
+            };
+            let (name, inline_value) = match name.split_once('=') {
+                Some((name, _)) => (name, true),
+                None => (name, false),
+            };
+            let option = match config.options.iter().find(|option| option.name == name) {
+                Some(option) => option,
+                None => continue,
+            };
+            value_expected = option.takes_argument && !inline_value;
+            if !option.list && !seen.insert(option.name) {
+                return Some(option.name);
+            }
+        }
+        None
+    }
+
+    // Turns the environment variables of all options into arguments, switches are passed with
+    // their value, so `resolve_switch_values` has to be applied to the result
+    fn env_args(config: &Configuration, prefix: &str) -> Vec<OsString> {
+        config
+            .sorted_options(|_| true)
+            .into_iter()
+            .filter_map(|option| {
+                let name = format!(
+                    "{}_{}",
+                    prefix,
+                    option.name.to_uppercase().replace('-', "_")
+                );
+                let value = env::var_os(name)?;
+                let mut arg = OsString::from(format!("--{}=", option.name));
+                arg.push(value);
+                Some(arg)
+            })
+            .collect()
+    }
+
+    // Turns the JSON object in the environment variable into arguments, switches are passed
+    // with their value like in `env_args`
+    fn json_env_args(json_env: &str) -> Result<Vec<OsString>, ConfigError> {
+        let invalid = |message: &str| ConfigError::InvalidConfigContent {
+            message: format!("{}: {}", json_env, message),
+        };
+        match env::var(json_env) {
+            Ok(content) if content.trim().is_empty() => Ok(vec![]),
+            Ok(content) => json::args(&content).map_err(|err| invalid(&err)),
+            Err(env::VarError::NotPresent) => Ok(vec![]),
+            Err(env::VarError::NotUnicode(_)) => Err(invalid("the value is not valid UTF-8")),
+        }
+    }
+
+    // Switches in the config file may be given an explicit value like `--switch=yes`, which
+    // clap doesn't accept, so these are replaced by `--switch` when the value is true
+    // The arguments are appended to those of the layers below, a false value removes the
+    // switch from these, so a higher layer can turn off a switch that a lower one turned on
+    fn resolve_switch_values(
+        config: &Configuration,
+        resolved: &mut Vec<OsString>,
+        args: Vec<OsString>,
+    ) -> Result<(), ConfigError> {
+        for arg in args {
+            let switch = arg.to_str().and_then(|arg| {
+                let (name, value) = arg.strip_prefix("--")?.split_once('=')?;
+                config
+                    .options
+                    .iter()
+                    .find(|option| !option.takes_argument && option.name == name)
+                    .map(|option| (option, value))
+            });
+            match switch {
+                Some((option, value)) => match parse_bool(value) {
+                    Some(true) => resolved.push(OsString::from(format!("--{}", option.name))),
+                    Some(false) => {
+                        let flag = format!("--{}", option.name);
+                        resolved.retain(|arg| arg.as_os_str() != flag.as_str());
+                    }
+                    None => {
+                        return Err(ConfigError::InvalidBool {
+                            name: option.name.to_string(),
+                            value: value.to_string(),
+                        })
+                    }
+                },
+                None => resolved.push(arg),
+            }
+        }
+        Ok(())
+    }
+
+    // Makes sure the config file can only be accessed by its owner, like ssh does for keys
+    // A missing file is not an error here, that is reported when reading it
+    #[cfg(unix)]
+    fn check_config_file_permissions(config_file_env: &str) -> Result<(), ConfigError> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = match env::var_os(config_file_env) {
+            Some(path) if !path.is_empty() => PathBuf::from(path),
+            _ => return Ok(()),
+        };
+        if let Ok(metadata) = std::fs::metadata(&path) {
+            let mode = metadata.permissions().mode() & 0o777;
+            if mode & 0o077 != 0 {
+                return Err(ConfigError::InsecureConfigPermissions { path, mode });
+            }
+        }
+        Ok(())
+    }
+
+    // There are no Unix permissions to check on other platforms
+    #[cfg(not(unix))]
+    fn check_config_file_permissions(_config_file_env: &str) -> Result<(), ConfigError> {
+        Ok(())
+    }
+
+    // Removes surrounding whitespace from the values of a `non_empty` option and rejects
+    // values that are empty afterwards, values that are not valid UTF-8 are kept as they are
+    fn trim_values(
+        option: &ConfigOption,
+        values: Vec<OsString>,
+    ) -> Result<Vec<OsString>, ConfigError> {
+        values
+            .into_iter()
+            .map(|value| {
+                let value = match value.to_str() {
+                    Some(text) => OsString::from(text.trim()),
+                    None => value,
+                };
+                if value.is_empty() {
+                    Err(ConfigError::EmptyValue {
+                        name: option.name.to_string(),
+                    })
+                } else {
+                    Ok(value)
+                }
+            })
+            .collect()
+    }
+
+    // Replaces a value of the form `@<file>` by the content of that file without surrounding
+    // whitespace, `@@` at the start of a value is an escaped literal `@`
+    // For `list_from_file` options every entry in the file becomes a value of its own
+    fn read_value_file(
+        config: &Configuration,
+        option: &ConfigOption,
+        value: OsString,
+    ) -> Result<Vec<OsString>, ConfigError> {
+        let file = match value.to_str() {
+            Some(value) if value.starts_with("@@") => return Ok(vec![OsString::from(&value[1..])]),
+            Some(value) if value.starts_with('@') => PathBuf::from(&value[1..]),
+            _ => return Ok(vec![value]),
+        };
+        match ConfigBuilder::read_file_or_fifo(config, &file) {
+            Ok(content) if option.list && option.list_from_file => Ok(content
+                .lines()
+                .flat_map(|line| line.split('#').next().unwrap_or(line).split(','))
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(OsString::from)
+                .collect()),
+            Ok(content) => Ok(vec![OsString::from(content.trim())]),
+            Err(source) if source.kind() == io::ErrorKind::TimedOut => {
+                Err(ConfigError::SecretSourceTimeout {
+                    name: option.name.to_string(),
+                    path: file,
+                })
+            }
+            Err(source) => Err(ConfigError::ValueFileUnreadable {
+                name: option.name.to_string(),
+                path: file,
+                source,
+            }),
+        }
+    }
+
+    #[cfg(unix)]
+    fn read_file_or_fifo(config: &Configuration, path: &Path) -> io::Result<String> {
+        if fifo::is_fifo(path) {
+            fifo::read_to_string(path, config.fifo_timeout.unwrap_or(DEFAULT_FIFO_TIMEOUT))
+        } else {
+            std::fs::read_to_string(path)
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn read_file_or_fifo(_config: &Configuration, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    // Joins relative paths to the base directory of the configuration
+    fn make_absolute(
+        config: &Configuration,
+        paths: Vec<OsString>,
+    ) -> Result<Vec<OsString>, ConfigError> {
+        let base = match &config.base_dir {
+            Some(base_dir) => base_dir.clone(),
+            None => env::current_dir().map_err(ConfigError::WorkingDirectory)?,
+        };
+        Ok(paths
+            .into_iter()
+            .map(|path| base.join(path).into_os_string())
+            .collect())
+    }
+
+    // Checks that the values of all options with a `path_kind` match the expectation
+    fn check_paths(config: &Configuration, values: &ResolvedValues) -> Result<(), ConfigError> {
+        for option in config.options.iter() {
+            if let Some(kind) = option.path_kind {
+                for value in values.get_os_values(option).unwrap_or_default() {
+                    let path = PathBuf::from(value);
+                    if !kind.matches(&path) {
+                        return Err(ConfigError::InvalidPath {
+                            name: option.name.to_string(),
+                            path,
+                            expected: kind,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Splits the arguments at the first `--`, which belongs to neither part
+    // The first element is the name of the binary and never taken for the separator
+    fn split_trailing_arguments(mut commandline: Vec<OsString>) -> (Vec<OsString>, Vec<OsString>) {
+        match commandline
+            .iter()
+            .skip(1)
+            .position(|argument| argument == "--")
+        {
+            Some(position) => {
+                let trailing = commandline.split_off(position + 2);
+                commandline.pop();
+                (commandline, trailing)
+            }
+            None => (commandline, vec![]),
+        }
+    }
+
+    // Splits the arguments into those that belong to the options of the configuration (plus
+    // clap's own help and version flags) and all others
+    // The first element is the name of the binary and always kept
+    fn split_unknown_arguments(
+        config: &Configuration,
+        commandline: Vec<OsString>,
+    ) -> (Vec<OsString>, Vec<OsString>) {
+        let mut arguments = commandline.into_iter();
+        let mut known: Vec<OsString> = arguments.next().into_iter().collect();
+        let mut unknown = vec![];
+
+        // Set if the previous argument was a known option whose value follows separately
+        let mut value_expected = false;
+        while let Some(argument) = arguments.next() {
+            if value_expected {
+                known.push(argument);
+                value_expected = false;
+                continue;
+            }
+            if argument == "--" {
+                unknown.push(argument);
+                unknown.extend(arguments.by_ref());
+                break;
+            }
+
+            let long_name = argument
+                .to_str()
+                .and_then(|argument| argument.strip_prefix("--"))
+                .map(|argument| argument.split('=').next().unwrap_or(argument));
+            let option =
+                long_name.and_then(|name| config.options.iter().find(|option| option.name == name));
+
+            if let Some(option) = option {
+                value_expected = option.takes_argument && !argument.to_string_lossy().contains('=');
+                known.push(argument);
+            } else if long_name.is_some_and(|name| RESERVED_FLAGS.contains(&name))
+                || matches!(argument.to_str(), Some("-h") | Some("-V"))
+            {
+                known.push(argument);
+            } else {
+                unknown.push(argument);
+            }
+        }
+        (known, unknown)
+    }
+
+    // Help and version output are reported as errors by clap as well, these are turned into
+    // a `ConfigOutcome` by the callers of `resolve`, see `requested_output`
+    fn clap_error(err: clap::Error) -> ConfigError {
+        ConfigError::InvalidArguments(err)
+    }
+
+    // Returns all required options that are not present in the arguments, sorted by name
+    fn missing_required<'a>(
+        config: &'a Configuration,
+        commandline: &[OsString],
+    ) -> Result<Vec<&'a ConfigOption>, ConfigError> {
+        let help_texts = ConfigBuilder::help_texts(config);
+        let matches = ConfigBuilder::create_relaxed_matcher(config, &help_texts)
+            .get_matches_from_safe(commandline.to_owned())
+            .map_err(ConfigBuilder::clap_error)?;
+
+        let mut missing: Vec<&ConfigOption> = config
+            .options
+            .iter()
+            .filter(|option| option.required && !matches.is_present(option.name))
+            .collect();
+        missing.sort();
+        Ok(missing)
+    }
+
+    // Create a clap matcher that treats all options as optional
+    // This is used whenever we need to look at the arguments before all values have been
+    // collected, e.g. because some of them will come from the config file
+    fn create_relaxed_matcher<'a>(config: &Configuration, help_texts: &'a [String]) -> App<'a, 'a> {
+        let relaxed = Configuration {
+            options: config
+                .options
+                .iter()
+                .map(|option| ConfigOption {
+                    required: false,
+                    requires: &[],
+                    ..option.clone()
+                })
+                .collect(),
+            required_one_of: vec![],
+            ..config.clone()
+        };
+        ConfigBuilder::create_matcher(&relaxed, help_texts)
+    }
+
+    // The help texts of all options sorted by name, including their example
+    // clap only borrows these, so they have to be kept alive by the caller as long as the
+    // matcher that is created from them with `create_matcher`
+    fn help_texts(config: &Configuration) -> Vec<String> {
+        config
+            .sorted_options(|_| true)
+            .into_iter()
+            .map(ConfigOption::help_with_example)
+            .collect()
     }
 
     // Create a clap matcher based on the ConfigOptions that were defined in the config object
-    fn create_matcher(config: &Configuration) -> App {
+    // `help_texts` are the help texts of the options as returned by `help_texts`
+    fn create_matcher<'a>(config: &Configuration, help_texts: &'a [String]) -> App<'a, 'a> {
         let mut matches = App::new(config.name)
             .version(config.version)
-            .about(config.about);
+            .about(config.about)
+            .settings(config.color.settings());
 
-        for option in config.options.iter() {
+        // Configurations that define these options themselves keep their own definition,
+        // clap doesn't allow two arguments with the same name
+        let defined = |name: &str| config.options.iter().any(|option| option.name == name);
+        if !defined(PRINT_CONFIG) {
+            matches = matches.arg(
+                Arg::with_name(PRINT_CONFIG)
+                    .long(PRINT_CONFIG)
+                    .help("Prints the resolved configuration and exits"),
+            );
+        }
+
+        if !defined(NO_CONFIG) {
+            matches = matches.arg(
+                Arg::with_name(NO_CONFIG)
+                    .long(NO_CONFIG)
+                    .help("Ignores the config file"),
+            );
+        }
+
+        // Only offer the flag if there is something to enable
+        if config.options.iter().any(|option| option.experimental) && !defined(ENABLE_EXPERIMENTAL)
+        {
+            matches = matches.arg(
+                Arg::with_name(ENABLE_EXPERIMENTAL)
+                    .long(ENABLE_EXPERIMENTAL)
+                    .help("Allows using options that are marked as experimental"),
+            );
+        }
+
+        // Sorted so that help and completions are the same on every run
+        for (option, help) in config.sorted_options(|_| true).into_iter().zip(help_texts) {
             let mut new_arg = Arg::with_name(option.name)
                 .long(option.name)
                 .value_name(option.name)
-                .help(option.help)
+                .help(help)
                 .takes_value(option.takes_argument)
                 .required(option.required);
 
+            if !option.requires.is_empty() {
+                new_arg = new_arg.requires_all(option.requires);
+            }
+
+            // clap only keeps the last validator of an argument, so all checks of the option
+            // have to run in one
+            let (address, is_base64, schemes) = (option.address, option.base64, option.url_schemes);
+            if address.is_some() || is_base64 || !schemes.is_empty() {
+                new_arg = new_arg.validator(move |value| {
+                    if let Some(kind) = address {
+                        kind.validate(&value)?;
+                    }
+                    if is_base64 {
+                        base64::validate(&value)?;
+                    }
+                    if !schemes.is_empty() {
+                        url::validate(&value, schemes)?;
+                    }
+                    Ok(())
+                });
+            }
+
+            if !option.possible_values.is_empty() {
+                new_arg = new_arg.possible_values(option.possible_values);
+            }
+
             // Was a default value specified for this option?
             if let Some(default_value) = &option.default {
                 // If this is an option that does not take an argument i.e. a switch
@@ -227,30 +1422,93 @@ impl ConfigBuilder {
                 matches = matches.arg(new_arg.overrides_with(option.name));
             }
         }
+
+        for group in config.required_one_of.iter() {
+            matches = matches.group(
+                ArgGroup::with_name(group.name)
+                    .args(group.options)
+                    .required(true),
+            );
+        }
         matches
     }
 
+    // Returns the combined arguments and what was read from the config files
     fn maybe_combine_arguments(
-        app_matcher: App,
+        config: &Configuration,
         commandline: &[OsString],
-        config_file_env: &str,
-    ) -> Result<Vec<OsString>, anyhow::Error> {
-        // Parse provided arguments
-        let command_line_args = app_matcher.get_matches_from(commandline.to_owned());
+        source: ConfigSource,
+    ) -> Result<(Vec<OsString>, FilesRead), ConfigError> {
+        // Later arguments take precedence, so the sources are collected from the lowest
+        // precedence to the highest
+        let mut args_from_file = vec![];
+        ConfigBuilder::resolve_switch_values(
+            config,
+            &mut args_from_file,
+            ripgrep_config::args_from_str(config.defaults),
+        )?;
+
+        let mut files = FilesRead::default();
+        if let Some(defaults_file) = config.defaults_file.as_deref().filter(|path| path.exists()) {
+            let (args, mut warnings) =
+                match ripgrep_config::args_from_file(defaults_file, config.config_file_encoding) {
+                    Ok(read) => read,
+                    // Like the config file, a defaults file that can't be read is ignored
+                    Err(err @ ConfigError::ConfigFileNotFound { .. }) => {
+                        error!("{:#}", err);
+                        Default::default()
+                    }
+                    Err(err) => return Err(err),
+                };
+            files.warnings.append(&mut warnings);
+            ConfigBuilder::resolve_switch_values(config, &mut args_from_file, args)?;
+        }
 
         // If --no-config was passed on the command line, we bypass reading values from the
         // extra config file
-        let mut args_from_file = if command_line_args.is_present("no-config") {
-            vec![]
-        } else {
-            ripgrep_config::args(config_file_env)
-        };
+        if !ConfigBuilder::no_config_requested(config, commandline) {
+            let mut args = match source {
+                ConfigSource::Env(config_file_env) => {
+                    if config.private_config_file {
+                        ConfigBuilder::check_config_file_permissions(config_file_env)?;
+                    }
+                    let (args, path, mut warnings) = ripgrep_config::args(
+                        config_file_env,
+                        config.config_file_encoding,
+                        config.strict_config_file,
+                    )?;
+                    files.config_file = path;
+                    files.warnings.append(&mut warnings);
+                    args
+                }
+                ConfigSource::Args(args) => args.to_vec(),
+            };
+            if config.interpolate_config_file {
+                args = interpolate::interpolate(args)?;
+            }
+            if let Some(config_dir_env) = config.config_dir_env {
+                args.extend(config_dir::args(config_dir_env));
+            }
+            ConfigBuilder::resolve_switch_values(config, &mut args_from_file, args)?;
+        }
 
-        // Check if there were any arguments in the config file
+        if let Some(json_env) = config.json_env {
+            let args = ConfigBuilder::json_env_args(json_env)?;
+            ConfigBuilder::resolve_switch_values(config, &mut args_from_file, args)?;
+        }
+
+        if let Some(prefix) = config.env_prefix {
+            let args = ConfigBuilder::env_args(config, prefix);
+            ConfigBuilder::resolve_switch_values(config, &mut args_from_file, args)?;
+        }
+
+        // Check if there were any arguments from other sources, there are none from the config
+        // file if the variable is unset or empty, or if the file is empty or only contains
+        // comments
         if args_from_file.is_empty() {
             // Return the command line arguments, as there is nothing to add to these
             // in this case
-            return Ok(commandline.to_owned());
+            return Ok((commandline.to_owned(), files));
         }
 
         // Build combined options from command line arguments and arguments parsed
@@ -268,7 +1526,16 @@ impl ConfigBuilder {
         args_from_file.extend(cliargs);
 
         // Return combined values
-        Ok(args_from_file)
+        Ok((args_from_file, files))
+    }
+}
+
+// Parses the common ways to write a boolean, ignoring case
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None,
     }
 }
 
@@ -276,10 +1543,20 @@ impl ConfigBuilder {
 mod tests {
     use std::ffi::OsString;
 
-    use crate::{ConfigBuilder, ConfigOption, Configurable, Configuration};
+    use crate::prompt::{NonInteractive, Prompt};
+    use crate::{
+        parse_config_reader, AddressKind, ColorChoice, ConfigBuilder, ConfigError,
+        ConfigFileEncoding, ConfigOption, ConfigOutcome, ConfigReloader, ConfigSource,
+        ConfigWarning, Configurable, Configuration, OptionGroup, PathKind, ResolvedValues,
+    };
     use std::collections::HashMap;
     use std::env;
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
     use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Mutex, PoisonError};
+    use std::time::Duration;
 
     static COUNTER: AtomicUsize = AtomicUsize::new(1);
 
@@ -306,6 +1583,7 @@ mod tests {
             help: "Testhelp",
             documentation: "Testdoc",
             list: false,
+            ..ConfigOption::DEFAULT
         };
         pub const TEST_PARAM2: ConfigOption = ConfigOption {
             name: "testparam2",
@@ -315,6 +1593,7 @@ mod tests {
             help: "test2",
             documentation: "test2",
             list: false,
+            ..ConfigOption::DEFAULT
         };
         pub const TEST_SWITCH: ConfigOption = ConfigOption {
             name: "testswitch",
@@ -324,6 +1603,7 @@ mod tests {
             help: "a switch that can be provided - or not",
             documentation: "test doc switch",
             list: false,
+            ..ConfigOption::DEFAULT
         };
         pub const TEST_MULTIPLE: ConfigOption = ConfigOption {
             name: "testmultiple",
@@ -333,6 +1613,7 @@ mod tests {
             help: "A parameter that can be specified multiple times and all values will be used.",
             documentation: "",
             list: true,
+            ..ConfigOption::DEFAULT
         };
 
         // This function retrieves a string value that is stored for the ConfigOption that
@@ -349,7 +1630,7 @@ mod tests {
                 .get(key)
                 .expect("Error retrieving value!")
                 .clone();
-            if value == None {
+            if value.is_none() {
                 panic!("Argument was not specified!");
             }
             let value = value.expect("Shouldn't happen");
@@ -361,12 +1642,10 @@ mod tests {
 
         // Helper function to check whether the argument was provided on the command line
         pub fn argument_was_provided(&self, key: &ConfigOption) -> bool {
-            matches!(
-                self.values
-                    .get(key)
-                    .expect("Fatal error: key not present in HashMap, but should have been!"),
-                Some(_)
-            )
+            self.values
+                .get(key)
+                .expect("Fatal error: key not present in HashMap, but should have been!")
+                .is_some()
         }
     }
 
@@ -386,6 +1665,7 @@ mod tests {
                 .iter()
                 .cloned()
                 .collect(),
+                ..Configuration::default()
             }
         }
 
@@ -520,6 +1800,624 @@ mod tests {
         assert!(config.argument_was_provided(&TestConfig::TEST_PARAM2));
     }
 
+    // Test that --no-config is honored before it is known whether required options are missing
+    #[test]
+    fn no_config_with_missing_required() {
+        struct RequiredParamConfig {
+            values: HashMap<ConfigOption, Option<Vec<String>>>,
+        }
+
+        impl Configurable for RequiredParamConfig {
+            fn get_config_description() -> Configuration {
+                Configuration {
+                    options: [
+                        TestConfig::TEST_PARAM,
+                        ConfigOption {
+                            required: true,
+                            ..TestConfig::TEST_PARAM2
+                        },
+                    ]
+                    .iter()
+                    .cloned()
+                    .collect(),
+                    name: "Test Tool",
+                    version: "0.1",
+                    ..Configuration::default()
+                }
+            }
+
+            fn parse_values(
+                values: HashMap<ConfigOption, Option<Vec<String>>>,
+            ) -> Result<Self, anyhow::Error> {
+                Ok(RequiredParamConfig { values })
+            }
+        }
+
+        let env_var_name = get_and_delete_env_var();
+        env::set_var(
+            &env_var_name,
+            get_absolute_file("resources/test/config1.conf"),
+        );
+
+        // The required option comes from the file
+        let config: RequiredParamConfig =
+            ConfigBuilder::build(vec![OsString::from("filename")], &env_var_name)
+                .expect("Error building config object!");
+        assert_eq!(
+            config.values.get(&TestConfig::TEST_PARAM2),
+            Some(&Some(vec![String::from("fromfile2")]))
+        );
+
+        // The value of an option that looks like the flag is not mistaken for it
+        let config: RequiredParamConfig = ConfigBuilder::build(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--testparam=--no-config"),
+            ],
+            &env_var_name,
+        )
+        .expect("Error building config object!");
+        assert_eq!(
+            config.values.get(&TestConfig::TEST_PARAM),
+            Some(&Some(vec![String::from("--no-config")]))
+        );
+        assert_eq!(
+            config.values.get(&TestConfig::TEST_PARAM2),
+            Some(&Some(vec![String::from("fromfile2")]))
+        );
+
+        // The file is ignored, so the required option is missing
+        let result: Result<RequiredParamConfig, ConfigError> = ConfigBuilder::build(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--testparam"),
+                OsString::from("fromcommandline"),
+                OsString::from("--no-config"),
+            ],
+            &env_var_name,
+        );
+        assert!(matches!(
+            result,
+            Err(ConfigError::MissingRequired { name }) if name == "testparam2"
+        ));
+    }
+
+    // Test that config files without any arguments leave the command line untouched
+    #[test]
+    fn config_file_without_arguments() {
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--testparam"),
+            OsString::from("param1"),
+        ];
+
+        for value in [
+            get_absolute_file("resources/test/comments_only.conf"),
+            get_absolute_file("resources/test/empty.conf"),
+            String::new(),
+        ] {
+            let env_var_name = get_and_delete_env_var();
+            env::set_var(&env_var_name, &value);
+
+            let (combined, _) = ConfigBuilder::maybe_combine_arguments(
+                &TestConfig::get_config_description(),
+                &command_line_args,
+                ConfigSource::Env(&env_var_name),
+            )
+            .expect("Error combining arguments");
+            assert_eq!(combined, command_line_args, "config file: '{}'", value);
+
+            let config: TestConfig = ConfigBuilder::build(command_line_args.clone(), &env_var_name)
+                .expect("Error building config object!");
+            assert_eq!(
+                config.get_first_and_only_value(&TestConfig::TEST_PARAM),
+                "param1"
+            );
+            assert!(!config.argument_was_provided(&TestConfig::TEST_PARAM2));
+        }
+    }
+
+    #[test]
+    fn parse_utf16_file() {
+        struct Utf16Config {
+            values: HashMap<ConfigOption, Option<Vec<String>>>,
+        }
+
+        impl Configurable for Utf16Config {
+            fn get_config_description() -> Configuration {
+                Configuration {
+                    config_file_encoding: ConfigFileEncoding::Utf16Le,
+                    ..TestConfig::get_config_description()
+                }
+            }
+
+            fn parse_values(
+                values: HashMap<ConfigOption, Option<Vec<String>>>,
+            ) -> Result<Self, anyhow::Error> {
+                Ok(Utf16Config { values })
+            }
+        }
+
+        let env_var_name = get_and_delete_env_var();
+        env::set_var(
+            &env_var_name,
+            get_absolute_file("resources/test/config_utf16le.conf"),
+        );
+
+        let config: Utf16Config =
+            ConfigBuilder::build(vec![OsString::from("filename")], &env_var_name)
+                .expect("Error building config object!");
+
+        assert_eq!(
+            config.values.get(&TestConfig::TEST_PARAM),
+            Some(&Some(vec![String::from("fromfile")]))
+        );
+        assert_eq!(
+            config.values.get(&TestConfig::TEST_PARAM2),
+            Some(&Some(vec![String::from("fromfile2")]))
+        );
+    }
+
+    #[test]
+    fn invalid_encoding() {
+        let env_var_name = get_and_delete_env_var();
+        let config_file = env::temp_dir().join(format!("{}.conf", env_var_name));
+        // `--` followed by an unpaired high surrogate
+        fs::write(&config_file, b"-\0-\0\x00\xD8").expect("Error writing config file");
+        env::set_var(&env_var_name, &config_file);
+
+        let config = Configuration {
+            config_file_encoding: ConfigFileEncoding::Utf16Le,
+            ..TestConfig::get_config_description()
+        };
+        let result = ConfigBuilder::maybe_combine_arguments(
+            &config,
+            &[OsString::from("filename")],
+            ConfigSource::Env(&env_var_name),
+        );
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidEncoding { path, encoding: ConfigFileEncoding::Utf16Le, offset: 4 })
+                if path == config_file
+        ));
+    }
+
+    // Test the precedence of all sources of values, from defaults to the command line
+    #[test]
+    fn layered_sources() {
+        struct LayeredConfig {
+            values: HashMap<ConfigOption, Option<Vec<String>>>,
+        }
+
+        const fn layer(name: &'static str) -> ConfigOption {
+            ConfigOption {
+                name,
+                takes_argument: true,
+                ..ConfigOption::DEFAULT
+            }
+        }
+        const DEFAULTS: ConfigOption = layer("from-defaults");
+        const FILE: ConfigOption = layer("from-file");
+        const ENV: ConfigOption = layer("from-env");
+        const COMMAND_LINE: ConfigOption = layer("from-command-line");
+
+        impl Configurable for LayeredConfig {
+            fn get_config_description() -> Configuration {
+                Configuration {
+                    options: [DEFAULTS, FILE, ENV, COMMAND_LINE]
+                        .iter()
+                        .cloned()
+                        .collect(),
+                    defaults: "--from-defaults=defaults\n--from-file=defaults\n\
+                               --from-env=defaults\n--from-command-line=defaults\n",
+                    env_prefix: Some("LAYERED_TEST"),
+                    name: "Test Tool",
+                    version: "0.1",
+                    ..Configuration::default()
+                }
+            }
+
+            fn parse_values(
+                values: HashMap<ConfigOption, Option<Vec<String>>>,
+            ) -> Result<Self, anyhow::Error> {
+                Ok(LayeredConfig { values })
+            }
+        }
+
+        let env_var_name = get_and_delete_env_var();
+        let config_file = env::temp_dir().join(format!("{}.conf", env_var_name));
+        fs::write(
+            &config_file,
+            "--from-file=file\n--from-env=file\n--from-command-line=file\n",
+        )
+        .expect("Error writing config file");
+        env::set_var(&env_var_name, &config_file);
+        env::set_var("LAYERED_TEST_FROM_ENV", "env");
+        env::set_var("LAYERED_TEST_FROM_COMMAND_LINE", "env");
+
+        let config: LayeredConfig = ConfigBuilder::build(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--from-command-line=command-line"),
+            ],
+            &env_var_name,
+        )
+        .expect("Error building config object!");
+
+        for (option, expected) in [
+            (DEFAULTS, "defaults"),
+            (FILE, "file"),
+            (ENV, "env"),
+            (COMMAND_LINE, "command-line"),
+        ] {
+            assert_eq!(
+                config.values.get(&option),
+                Some(&Some(vec![String::from(expected)])),
+                "{}",
+                option.name
+            );
+        }
+        fs::remove_file(config_file).expect("Error removing config file");
+    }
+
+    // Test that errors which are only logged carry their code in the log record
+    #[test]
+    fn logged_error_code() {
+        struct Recorder;
+
+        static RECORDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+        impl log::Log for Recorder {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                RECORDS
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(record.args().to_string());
+            }
+
+            fn flush(&self) {}
+        }
+
+        // Other tests don't install a logger, so this only fails if the test runs twice
+        log::set_logger(&Recorder).expect("Error installing logger");
+        log::set_max_level(log::LevelFilter::Error);
+
+        // A directory can't be read as config file, which is logged and ignored
+        let env_var_name = get_and_delete_env_var();
+        let dir = env::temp_dir();
+        env::set_var(&env_var_name, &dir);
+        ConfigBuilder::maybe_combine_arguments(
+            &TestConfig::get_config_description(),
+            &[OsString::from("filename")],
+            ConfigSource::Env(&env_var_name),
+        )
+        .expect("Error combining arguments");
+
+        let expected = format!("E0013: failed to open config file '{}'", dir.display());
+        assert!(RECORDS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .any(|record| record.starts_with(&expected)));
+    }
+
+    // Test that a switch turned on by a lower layer can be turned off by a higher one
+    #[test]
+    fn layered_switches() {
+        const PREFIX: &str = "LAYERED_SWITCH_TEST";
+        const JSON_ENV: &str = "LAYERED_SWITCH_TEST_JSON";
+        let switch_on = |config_file: &str, json: &str, env: Option<&str>| {
+            let env_var_name = get_and_delete_env_var();
+            let file = env::temp_dir().join(format!("{}.conf", env_var_name));
+            fs::write(&file, config_file).expect("Error writing config file");
+            env::set_var(&env_var_name, &file);
+            env::set_var(JSON_ENV, json);
+            match env {
+                Some(value) => env::set_var("LAYERED_SWITCH_TEST_TESTSWITCH", value),
+                None => env::remove_var("LAYERED_SWITCH_TEST_TESTSWITCH"),
+            }
+
+            let description = Configuration {
+                defaults: "--testswitch",
+                env_prefix: Some(PREFIX),
+                json_env: Some(JSON_ENV),
+                ..TestConfig::get_config_description()
+            };
+            let (combined, _) = ConfigBuilder::maybe_combine_arguments(
+                &description,
+                &[OsString::from("filename")],
+                ConfigSource::Env(&env_var_name),
+            )
+            .expect("Error combining arguments");
+            fs::remove_file(file).expect("Error removing config file");
+            combined.contains(&OsString::from("--testswitch"))
+        };
+
+        // The environment turns off what the defaults turned on
+        assert!(!switch_on("", "", Some("false")));
+        // So does the JSON object
+        assert!(!switch_on("", r#"{"testswitch": false}"#, None));
+        // And the config file
+        assert!(!switch_on("--testswitch=no\n", "", None));
+        // The environment is above the JSON object and the config file
+        assert!(switch_on(
+            "--testswitch=no\n",
+            r#"{"testswitch": false}"#,
+            Some("yes")
+        ));
+        assert!(!switch_on(
+            "--testswitch=yes\n",
+            r#"{"testswitch": true}"#,
+            Some("0")
+        ));
+        assert!(switch_on("", "", None));
+
+        env::remove_var(JSON_ENV);
+        env::remove_var("LAYERED_SWITCH_TEST_TESTSWITCH");
+    }
+
+    // Test that options can be set with a JSON object in an environment variable
+    #[test]
+    fn parse_from_json_env() {
+        const JSON_ENV: &str = "STACKABLE_CONFIG_TEST_JSON";
+        let env_var_name = get_and_delete_env_var();
+        env::set_var(
+            &env_var_name,
+            get_absolute_file("resources/test/config1.conf"),
+        );
+        env::set_var(
+            JSON_ENV,
+            r#"{"testparam": "fromjson", "testparam2": "fromjson2", "testmultiple": ["1", 2],
+                "testswitch": true}"#,
+        );
+
+        let description = Configuration {
+            json_env: Some(JSON_ENV),
+            ..TestConfig::get_config_description()
+        };
+        let resolve = |commandline: Vec<OsString>| {
+            ConfigBuilder::resolve(
+                &description,
+                commandline,
+                &env_var_name,
+                &mut NonInteractive,
+                false,
+            )
+        };
+
+        let values = resolve(vec![
+            OsString::from("filename"),
+            OsString::from("--testparam=fromcommandline"),
+        ])
+        .expect("Error resolving values")
+        .values;
+        assert_eq!(
+            values.get(&TestConfig::TEST_PARAM),
+            Some(&Some(vec![String::from("fromcommandline")]))
+        );
+        assert_eq!(
+            values.get(&TestConfig::TEST_PARAM2),
+            Some(&Some(vec![String::from("fromjson2")]))
+        );
+        assert_eq!(
+            values.get(&TestConfig::TEST_MULTIPLE),
+            Some(&Some(vec![String::from("1"), String::from("2")]))
+        );
+        assert_eq!(values.get(&TestConfig::TEST_SWITCH), Some(&Some(vec![])));
+
+        env::set_var(JSON_ENV, r#"{"testparam": "fromjson""#);
+        let result = resolve(vec![OsString::from("filename")]);
+        env::remove_var(JSON_ENV);
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidConfigContent { message })
+                if message.starts_with(JSON_ENV)
+        ));
+    }
+
+    // Test that values are read from a directory with one file per option
+    #[test]
+    fn parse_from_config_dir() {
+        const DIR_ENV_VAR_NAME: &str = "stackable-config-test-dir";
+        let env_var_name = get_and_delete_env_var();
+
+        let dir = env::temp_dir().join(DIR_ENV_VAR_NAME);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("Error creating test directory");
+        std::fs::write(dir.join("testparam2"), "fromdir\n").expect("Error writing test file");
+        std::fs::write(dir.join("testswitch"), "true\n").expect("Error writing test file");
+        env::set_var(DIR_ENV_VAR_NAME, &dir);
+        env::set_var(
+            &env_var_name,
+            get_absolute_file("resources/test/config1.conf"),
+        );
+
+        let description = Configuration {
+            config_dir_env: Some(DIR_ENV_VAR_NAME),
+            ..TestConfig::get_config_description()
+        };
+        let (commandline, _) = ConfigBuilder::maybe_combine_arguments(
+            &description,
+            &[OsString::from("filename")],
+            ConfigSource::Env(&env_var_name),
+        )
+        .expect("Error combining arguments");
+
+        // The directory comes after the config file, so its values win
+        assert_eq!(
+            commandline,
+            vec![
+                OsString::from("filename"),
+                OsString::from("--testparam=fromfile"),
+                OsString::from("--testparam2=fromfile2"),
+                OsString::from("--testparam2=fromdir"),
+                OsString::from("--testswitch"),
+            ]
+        );
+        std::fs::remove_dir_all(dir).expect("Error removing test directory");
+    }
+
+    #[test]
+    fn strict_config_file() {
+        let description = Configuration {
+            strict_config_file: true,
+            ..TestConfig::get_config_description()
+        };
+        let command_line_args = vec![OsString::from("filename")];
+
+        // Not specifying a config file at all is fine
+        let env_var_name = get_and_delete_env_var();
+        let (result, _) = ConfigBuilder::maybe_combine_arguments(
+            &description,
+            &command_line_args,
+            ConfigSource::Env(&env_var_name),
+        )
+        .expect("Error combining arguments");
+        assert_eq!(result, command_line_args);
+
+        let missing = env::temp_dir().join(format!("{}-missing.conf", env_var_name));
+        env::set_var(&env_var_name, &missing);
+        let result = ConfigBuilder::maybe_combine_arguments(
+            &description,
+            &command_line_args,
+            ConfigSource::Env(&env_var_name),
+        );
+        assert!(matches!(
+            result,
+            Err(ConfigError::ConfigFileNotFound { path, .. }) if path == missing
+        ));
+
+        // Without strict mode the missing file is ignored
+        let (result, _) = ConfigBuilder::maybe_combine_arguments(
+            &TestConfig::get_config_description(),
+            &command_line_args,
+            ConfigSource::Env(&env_var_name),
+        )
+        .expect("Error combining arguments");
+        assert_eq!(result, command_line_args);
+
+        // A directory can be opened but not read, the error comes from reading it
+        let dir = env::temp_dir();
+        env::set_var(&env_var_name, &dir);
+        let result = ConfigBuilder::maybe_combine_arguments(
+            &description,
+            &command_line_args,
+            ConfigSource::Env(&env_var_name),
+        );
+        assert!(matches!(
+            result,
+            Err(ConfigError::ConfigFileNotFound { path, .. }) if path == dir
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn private_config_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let env_var_name = get_and_delete_env_var();
+        let config_file = env::temp_dir().join(format!("{}.conf", env_var_name));
+        std::fs::write(&config_file, "--testparam=fromfile\n").expect("Error writing config file");
+        env::set_var(&env_var_name, &config_file);
+
+        let description = Configuration {
+            private_config_file: true,
+            ..TestConfig::get_config_description()
+        };
+        let command_line_args = vec![OsString::from("filename")];
+
+        std::fs::set_permissions(&config_file, std::fs::Permissions::from_mode(0o644))
+            .expect("Error setting permissions");
+        let result = ConfigBuilder::maybe_combine_arguments(
+            &description,
+            &command_line_args,
+            ConfigSource::Env(&env_var_name),
+        );
+        assert!(matches!(
+            result,
+            Err(ConfigError::InsecureConfigPermissions { path, mode: 0o644 }) if path == config_file
+        ));
+
+        std::fs::set_permissions(&config_file, std::fs::Permissions::from_mode(0o600))
+            .expect("Error setting permissions");
+        let (result, _) = ConfigBuilder::maybe_combine_arguments(
+            &description,
+            &command_line_args,
+            ConfigSource::Env(&env_var_name),
+        )
+        .expect("Error combining arguments");
+        assert_eq!(
+            result,
+            vec![
+                OsString::from("filename"),
+                OsString::from("--testparam=fromfile")
+            ]
+        );
+
+        std::fs::remove_file(config_file).expect("Error removing config file");
+    }
+
+    #[test]
+    fn parse_bool() {
+        for value in ["true", "YES", "On", "1"] {
+            assert_eq!(super::parse_bool(value), Some(true), "{}", value);
+        }
+        for value in ["false", "No", "OFF", "0"] {
+            assert_eq!(super::parse_bool(value), Some(false), "{}", value);
+        }
+        assert_eq!(super::parse_bool("maybe"), None);
+    }
+
+    // Test that switches can be set to true or false explicitly in the config file
+    #[test]
+    fn switch_value_from_file() {
+        let env_var_name = get_and_delete_env_var();
+        let config_file = env::temp_dir().join(format!("{}.conf", env_var_name));
+        env::set_var(&env_var_name, &config_file);
+
+        for (value, expected) in [("yes", true), ("off", false), ("1", true), ("FALSE", false)] {
+            std::fs::write(&config_file, format!("--testswitch={}\n", value))
+                .expect("Error writing config file");
+            let config: TestConfig =
+                ConfigBuilder::build(vec![OsString::from("filename")], &env_var_name)
+                    .expect("Error building config object!");
+            assert_eq!(
+                config.argument_was_provided(&TestConfig::TEST_SWITCH),
+                expected,
+                "{}",
+                value
+            );
+        }
+
+        std::fs::write(&config_file, "--testswitch=maybe\n").expect("Error writing config file");
+        let result: Result<TestConfig, ConfigError> =
+            ConfigBuilder::build(vec![OsString::from("filename")], &env_var_name);
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidBool { name, value }) if name == "testswitch" && value == "maybe"
+        ));
+
+        // A false value turns off the switch if the defaults turned it on
+        std::fs::write(&config_file, "--testswitch=off\n").expect("Error writing config file");
+        let description = Configuration {
+            defaults: "--testswitch",
+            ..TestConfig::get_config_description()
+        };
+        let (combined, _) = ConfigBuilder::maybe_combine_arguments(
+            &description,
+            &[OsString::from("filename")],
+            ConfigSource::Env(&env_var_name),
+        )
+        .expect("Error combining arguments");
+        assert_eq!(combined, vec![OsString::from("filename")]);
+
+        std::fs::remove_file(config_file).expect("Error removing config file");
+    }
+
     /// This test case specifies the same parameter in a config file and on the command line
     /// Expected result is that command line parameter overrides the file.
     /// To ensure the file is not simply ignored a second parameter is loaded from file only.
@@ -582,29 +2480,1906 @@ mod tests {
         assert!(result.contains(&String::from("3")));
     }
 
-    /// Helper function to convert a filename that is relative to the config crate Cargo.toml
-    /// file to an absolute path by retrieving the CARGO_MANIFEST_DIR environment variable
-    /// and prepending this to the filename
-    ///
-    /// * `filename` - A relative filename (no leading /)
-    fn get_absolute_file(filename: &str) -> String {
-        env!("CARGO_MANIFEST_DIR").to_owned() + "/" + filename
+    // Test that list values are returned in exactly the order in which they were specified
+    #[test]
+    fn test_multiple_values_keep_order() {
+        let env_var_name = get_and_delete_env_var();
+
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--testmultiple"),
+            OsString::from("3"),
+            OsString::from("--testmultiple"),
+            OsString::from("1"),
+            OsString::from("--testmultiple"),
+            OsString::from("2"),
+        ];
+        let config: TestConfig = ConfigBuilder::build(command_line_args, &env_var_name)
+            .expect("Error building config object!");
+        let result = config
+            .values
+            .get(&TestConfig::TEST_MULTIPLE)
+            .expect("error getting value")
+            .clone();
+        assert_eq!(
+            result,
+            Some(vec![
+                String::from("3"),
+                String::from("1"),
+                String::from("2")
+            ])
+        );
     }
 
+    // Test that list values from the config file come first, followed by the values from the
+    // command line, each in the order in which they were specified
     #[test]
-    fn config_option_has_default() {
-        let default = ConfigOption::default();
-        assert_eq!(
-            default,
-            ConfigOption {
-                name: "",
-                default: None,
-                required: false,
-                takes_argument: false,
-                help: "",
-                documentation: "",
-                list: false
-            }
-        )
+    fn test_multiple_values_keep_order_with_file() {
+        let env_var_name = get_and_delete_env_var();
+
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--testmultiple"),
+            OsString::from("2"),
+            OsString::from("--testmultiple"),
+            OsString::from("1"),
+        ];
+
+        env::set_var(
+            &env_var_name,
+            get_absolute_file("resources/test/config_list.conf"),
+        );
+
+        let config: TestConfig = ConfigBuilder::build(command_line_args, &env_var_name)
+            .expect("Error building config object!");
+        let result = config
+            .values
+            .get(&TestConfig::TEST_MULTIPLE)
+            .expect("error getting value")
+            .clone();
+        assert_eq!(
+            result,
+            Some(vec![
+                String::from("fromfile2"),
+                String::from("fromfile1"),
+                String::from("2"),
+                String::from("1")
+            ])
+        );
+    }
+
+    // Test that an empty value discards the values of a list option from the config file
+    #[test]
+    fn clear_multiple_values_from_file() {
+        let env_var_name = get_and_delete_env_var();
+        env::set_var(
+            &env_var_name,
+            get_absolute_file("resources/test/config_list.conf"),
+        );
+
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--testmultiple="),
+            OsString::from("--testmultiple"),
+            OsString::from("new"),
+        ];
+        let config: TestConfig = ConfigBuilder::build(command_line_args, &env_var_name)
+            .expect("Error building config object!");
+        assert_eq!(
+            config.values.get(&TestConfig::TEST_MULTIPLE),
+            Some(&Some(vec![String::from("new")]))
+        );
+
+        // Clearing without a new value leaves the option unset
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--testmultiple="),
+        ];
+        let config: TestConfig = ConfigBuilder::build(command_line_args, &env_var_name)
+            .expect("Error building config object!");
+        assert_eq!(config.values.get(&TestConfig::TEST_MULTIPLE), Some(&None));
+    }
+
+    // Test that duplicate values of a list option are removed across sources
+    #[test]
+    fn dedup_multiple_values() {
+        struct DedupConfig {
+            values: HashMap<ConfigOption, Option<Vec<String>>>,
+        }
+
+        impl Configurable for DedupConfig {
+            fn get_config_description() -> Configuration {
+                Configuration {
+                    options: [ConfigOption {
+                        dedup_list: true,
+                        ..TestConfig::TEST_MULTIPLE
+                    }]
+                    .iter()
+                    .cloned()
+                    .collect(),
+                    name: "Test Tool",
+                    version: "0.1",
+                    ..Configuration::default()
+                }
+            }
+
+            fn parse_values(
+                values: HashMap<ConfigOption, Option<Vec<String>>>,
+            ) -> Result<Self, anyhow::Error> {
+                Ok(DedupConfig { values })
+            }
+        }
+
+        let env_var_name = get_and_delete_env_var();
+        env::set_var(
+            &env_var_name,
+            get_absolute_file("resources/test/config_list.conf"),
+        );
+
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--testmultiple=fromfile1"),
+            OsString::from("--testmultiple=new"),
+            OsString::from("--testmultiple=fromfile2"),
+            OsString::from("--testmultiple=new"),
+        ];
+        let config: DedupConfig = ConfigBuilder::build(command_line_args, &env_var_name)
+            .expect("Error building config object!");
+        assert_eq!(
+            config.values.get(&TestConfig::TEST_MULTIPLE),
+            Some(&Some(vec![
+                String::from("fromfile2"),
+                String::from("fromfile1"),
+                String::from("new")
+            ]))
+        );
+    }
+
+    // Test that values of non_empty options are trimmed and empty ones are rejected
+    #[test]
+    fn non_empty_value() {
+        const NAME: ConfigOption = ConfigOption {
+            name: "name",
+            takes_argument: true,
+            required: true,
+            non_empty: true,
+            ..ConfigOption::DEFAULT
+        };
+        let description = Configuration {
+            options: [NAME].iter().cloned().collect(),
+            name: "Test Tool",
+            version: "0.1",
+            ..Configuration::default()
+        };
+        let resolve = |value: &str| {
+            ConfigBuilder::resolve(
+                &description,
+                vec![
+                    OsString::from("filename"),
+                    OsString::from("--name"),
+                    value.into(),
+                ],
+                &get_and_delete_env_var(),
+                &mut NonInteractive,
+                false,
+            )
+        };
+
+        for value in &["", " \t "] {
+            assert!(matches!(
+                resolve(value),
+                Err(ConfigError::EmptyValue { name }) if name == "name"
+            ));
+        }
+
+        let resolved = resolve("  tool ").expect("Error resolving values");
+        assert_eq!(
+            resolved.values.get(&NAME),
+            Some(&Some(vec![String::from("tool")]))
+        );
+    }
+
+    // Test that values of options with url_schemes are checked while parsing
+    #[test]
+    fn url_option() {
+        let description = Configuration {
+            options: [ConfigOption {
+                name: "endpoint",
+                takes_argument: true,
+                url_schemes: &["https"],
+                ..ConfigOption::DEFAULT
+            }]
+            .iter()
+            .cloned()
+            .collect(),
+            name: "Test Tool",
+            version: "0.1",
+            ..Configuration::default()
+        };
+        let resolve = |value: &str| {
+            ConfigBuilder::resolve(
+                &description,
+                vec![
+                    OsString::from("filename"),
+                    OsString::from("--endpoint"),
+                    value.into(),
+                ],
+                &get_and_delete_env_var(),
+                &mut NonInteractive,
+                false,
+            )
+        };
+
+        assert!(resolve("https://example.com").is_ok());
+        for value in &["http://example.com", "example.com"] {
+            assert!(matches!(
+                resolve(value),
+                Err(ConfigError::InvalidArguments(err)) if err.message.contains(value)
+            ));
+        }
+    }
+
+    // Test that --print-config renders the values resolved from file and command line
+    #[test]
+    fn print_config() {
+        let env_var_name = get_and_delete_env_var();
+
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--print-config"),
+            OsString::from("--testparam"),
+            OsString::from("param1"),
+            OsString::from("--testswitch"),
+        ];
+
+        env::set_var(
+            &env_var_name,
+            get_absolute_file("resources/test/config1.conf"),
+        );
+
+        let outcome: ConfigOutcome<TestConfig> =
+            ConfigBuilder::build_safe(command_line_args, &env_var_name)
+                .expect("Error building config object!");
+        match outcome {
+            ConfigOutcome::PrintConfig(rendered) => assert_eq!(
+                rendered,
+                "--testmultiple=3\n--testparam=param1\n--testparam2=fromfile2\n--testswitch\n"
+            ),
+            _ => panic!("Expected the config to be printed"),
+        }
+    }
+
+    #[test]
+    fn no_print_config() {
+        let env_var_name = get_and_delete_env_var();
+
+        let command_line_args: Vec<OsString> = vec![OsString::from("filename")];
+        let outcome: ConfigOutcome<TestConfig> =
+            ConfigBuilder::build_safe(command_line_args, &env_var_name)
+                .expect("Error building config object!");
+        assert!(matches!(outcome, ConfigOutcome::Config(_)));
+    }
+
+    #[test]
+    fn help_and_version_requested() {
+        let env_var_name = get_and_delete_env_var();
+
+        let outcome: ConfigOutcome<TestConfig> = ConfigBuilder::build_safe(
+            vec![OsString::from("filename"), OsString::from("--help")],
+            &env_var_name,
+        )
+        .expect("Error building config object!");
+        match outcome {
+            ConfigOutcome::HelpRequested(help) => assert!(help.contains("USAGE")),
+            _ => panic!("Expected the help to be returned"),
+        }
+
+        let outcome: ConfigOutcome<TestConfig> = ConfigBuilder::build_safe(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--testparam"),
+                OsString::from("-V"),
+                OsString::from("--version"),
+            ],
+            &env_var_name,
+        )
+        .expect("Error building config object!");
+        let description = TestConfig::get_config_description();
+        match outcome {
+            ConfigOutcome::VersionRequested(version) => assert_eq!(
+                version,
+                format!("{} {}", description.name, description.version)
+            ),
+            _ => panic!("Expected the version to be returned"),
+        }
+    }
+
+    // Test that a path that is not valid UTF-8 is passed through unchanged
+    #[test]
+    #[cfg(unix)]
+    fn parse_non_utf8_path() {
+        use std::os::unix::ffi::OsStringExt;
+        use std::path::PathBuf;
+
+        struct PathConfig {
+            values: ResolvedValues,
+        }
+
+        impl PathConfig {
+            const DATA_DIR: ConfigOption = ConfigOption {
+                name: "data-dir",
+                takes_argument: true,
+                path: true,
+                ..ConfigOption::DEFAULT
+            };
+        }
+
+        impl Configurable for PathConfig {
+            fn get_config_description() -> Configuration {
+                Configuration {
+                    options: [PathConfig::DATA_DIR].iter().cloned().collect(),
+                    name: "Test Tool",
+                    version: "0.1",
+                    ..Configuration::default()
+                }
+            }
+
+            fn parse_values(
+                _: HashMap<ConfigOption, Option<Vec<String>>>,
+            ) -> Result<Self, anyhow::Error> {
+                unreachable!("parse_resolved is overridden")
+            }
+
+            // The values are not valid UTF-8, so the HashMap is not enough
+            fn parse_resolved(values: ResolvedValues) -> Result<Self, anyhow::Error> {
+                Ok(PathConfig { values })
+            }
+        }
+
+        let env_var_name = get_and_delete_env_var();
+        let path = OsString::from_vec(b"/var/lib/\xFFdata".to_vec());
+
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--data-dir"),
+            path.clone(),
+        ];
+        let config: PathConfig = ConfigBuilder::build(command_line_args, &env_var_name)
+            .expect("Error building config object!");
+
+        assert_eq!(
+            config.values.get_os_path(&PathConfig::DATA_DIR),
+            Some(PathBuf::from(path))
+        );
+    }
+
+    // Test that experimental options can only be used when explicitly enabled
+    #[test]
+    fn experimental_option() {
+        struct ExperimentalConfig;
+
+        impl ExperimentalConfig {
+            const UNSTABLE: ConfigOption = ConfigOption {
+                name: "unstable",
+                takes_argument: true,
+                default: Some("off"),
+                experimental: true,
+                ..ConfigOption::DEFAULT
+            };
+        }
+
+        impl Configurable for ExperimentalConfig {
+            fn get_config_description() -> Configuration {
+                Configuration {
+                    options: [ExperimentalConfig::UNSTABLE].iter().cloned().collect(),
+                    name: "Test Tool",
+                    version: "0.1",
+                    ..Configuration::default()
+                }
+            }
+
+            fn parse_values(
+                _: HashMap<ConfigOption, Option<Vec<String>>>,
+            ) -> Result<Self, anyhow::Error> {
+                Ok(ExperimentalConfig)
+            }
+        }
+
+        let env_var_name = get_and_delete_env_var();
+
+        // The default value alone does not need the opt-in
+        let result: Result<ExperimentalConfig, ConfigError> =
+            ConfigBuilder::build(vec![OsString::from("filename")], &env_var_name);
+        assert!(result.is_ok());
+
+        let result: Result<ExperimentalConfig, ConfigError> = ConfigBuilder::build(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--unstable"),
+                OsString::from("on"),
+            ],
+            &env_var_name,
+        );
+        assert!(matches!(
+            result,
+            Err(ConfigError::ExperimentalNotEnabled { name }) if name == "unstable"
+        ));
+
+        let result: Result<ExperimentalConfig, ConfigError> = ConfigBuilder::build(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--unstable"),
+                OsString::from("on"),
+                OsString::from("--enable-experimental"),
+            ],
+            &env_var_name,
+        );
+        assert!(result.is_ok());
+    }
+
+    // Test that the color choice is honored in the help output
+    #[test]
+    fn help_colors() {
+        let help = |color| {
+            let description = Configuration {
+                color,
+                ..TestConfig::get_config_description()
+            };
+            let mut out = vec![];
+            let help_texts = ConfigBuilder::help_texts(&description);
+            ConfigBuilder::create_matcher(&description, &help_texts)
+                .write_help(&mut out)
+                .expect("Error writing help");
+            String::from_utf8(out).expect("Help is not valid UTF-8")
+        };
+
+        let never = help(ColorChoice::Never);
+        assert!(never.contains("--testparam"));
+        assert!(!never.contains('\x1b'));
+        assert!(help(ColorChoice::Always).contains('\x1b'));
+    }
+
+    // Test that a repeated option is only an error if duplicates are rejected
+    #[test]
+    fn duplicate_flags() {
+        let env_var_name = get_and_delete_env_var();
+        env::set_var(
+            &env_var_name,
+            get_absolute_file("resources/test/config1.conf"),
+        );
+        let resolve = |reject_duplicate_flags| {
+            let description = Configuration {
+                reject_duplicate_flags,
+                ..TestConfig::get_config_description()
+            };
+            ConfigBuilder::resolve(
+                &description,
+                vec![
+                    OsString::from("filename"),
+                    OsString::from("--testparam"),
+                    OsString::from("first"),
+                    OsString::from("--testmultiple=1"),
+                    OsString::from("--testmultiple=2"),
+                    OsString::from("--testparam=second"),
+                ],
+                &env_var_name,
+                &mut NonInteractive,
+                false,
+            )
+        };
+
+        let resolved = resolve(false).expect("Error resolving values");
+        assert_eq!(
+            resolved.values.get(&TestConfig::TEST_PARAM),
+            Some(&Some(vec![String::from("second")]))
+        );
+        assert!(matches!(
+            resolve(true),
+            Err(ConfigError::DuplicateFlag { name }) if name == "testparam"
+        ));
+    }
+
+    // Test that the example of an option is appended to its help text
+    // Test that an invalid description is rejected before anything is parsed
+    #[test]
+    fn invalid_description() {
+        struct NamelessConfig;
+
+        impl Configurable for NamelessConfig {
+            fn get_config_description() -> Configuration {
+                Configuration {
+                    name: "",
+                    ..TestConfig::get_config_description()
+                }
+            }
+
+            fn parse_values(
+                _: HashMap<ConfigOption, Option<Vec<String>>>,
+            ) -> Result<Self, anyhow::Error> {
+                Ok(NamelessConfig)
+            }
+        }
+
+        let result: Result<NamelessConfig, ConfigError> =
+            ConfigBuilder::build(vec![OsString::from("filename")], &get_and_delete_env_var());
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidMetadata { field: "name" })
+        ));
+
+        // clap would panic on the unknown option in `requires`
+        let description = Configuration {
+            options: [ConfigOption {
+                name: "user",
+                takes_argument: true,
+                requires: &["pasword"],
+                ..ConfigOption::DEFAULT
+            }]
+            .iter()
+            .cloned()
+            .collect(),
+            ..TestConfig::get_config_description()
+        };
+        let result = ConfigBuilder::resolve(
+            &description,
+            vec![OsString::from("filename"), OsString::from("--user=admin")],
+            &get_and_delete_env_var(),
+            &mut NonInteractive,
+            false,
+        );
+        assert!(matches!(
+            result,
+            Err(ConfigError::DanglingReference {
+                from: "user",
+                to: "pasword"
+            })
+        ));
+    }
+
+    // Test that a configuration can define an option with the name of a reserved flag
+    // without clashing with the flag that is otherwise added
+    #[test]
+    fn reserved_flag_defined() {
+        let description = Configuration {
+            options: [ConfigOption {
+                name: "print-config",
+                help: "Shows the configuration",
+                ..ConfigOption::DEFAULT
+            }]
+            .iter()
+            .cloned()
+            .collect(),
+            name: "Test Tool",
+            version: "0.1",
+            ..Configuration::default()
+        };
+        let mut out = vec![];
+        let help_texts = ConfigBuilder::help_texts(&description);
+        ConfigBuilder::create_matcher(&description, &help_texts)
+            .write_help(&mut out)
+            .expect("Error writing help");
+        let help = String::from_utf8(out).expect("Help is not valid UTF-8");
+        assert!(help.contains("Shows the configuration"), "{}", help);
+        assert!(
+            !help.contains("Prints the resolved configuration"),
+            "{}",
+            help
+        );
+
+        let resolved = ConfigBuilder::resolve(
+            &description,
+            vec![OsString::from("filename"), OsString::from("--print-config")],
+            &get_and_delete_env_var(),
+            &mut NonInteractive,
+            false,
+        )
+        .expect("Error resolving values");
+        assert!(resolved.print_config);
+
+        // The configuration's own flag enables experimental options
+        let description = Configuration {
+            options: [
+                ConfigOption {
+                    name: "enable-experimental",
+                    ..ConfigOption::DEFAULT
+                },
+                ConfigOption {
+                    name: "unstable",
+                    experimental: true,
+                    ..ConfigOption::DEFAULT
+                },
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            name: "Test Tool",
+            version: "0.1",
+            ..Configuration::default()
+        };
+        ConfigBuilder::resolve(
+            &description,
+            vec![
+                OsString::from("filename"),
+                OsString::from("--enable-experimental"),
+                OsString::from("--unstable"),
+            ],
+            &get_and_delete_env_var(),
+            &mut NonInteractive,
+            false,
+        )
+        .expect("Error resolving values");
+    }
+
+    #[test]
+    fn help_example() {
+        let description = Configuration {
+            options: [
+                ConfigOption {
+                    name: "timeout",
+                    takes_argument: true,
+                    help: "Request timeout",
+                    example: Some("30s"),
+                    ..ConfigOption::DEFAULT
+                },
+                ConfigOption {
+                    name: "retries",
+                    takes_argument: true,
+                    help: "Number of retries",
+                    ..ConfigOption::DEFAULT
+                },
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            ..Configuration::default()
+        };
+        let mut out = vec![];
+        let help_texts = ConfigBuilder::help_texts(&description);
+        ConfigBuilder::create_matcher(&description, &help_texts)
+            .write_help(&mut out)
+            .expect("Error writing help");
+        let help = String::from_utf8(out).expect("Help is not valid UTF-8");
+
+        assert!(help.contains("Request timeout (e.g. 30s)"), "{}", help);
+        assert!(help.contains("Number of retries\n"), "{}", help);
+    }
+
+    #[test]
+    fn required_and_optional_options() {
+        let description = Configuration {
+            options: [
+                TestConfig::TEST_PARAM,
+                RequiredConfig::USER,
+                TestConfig::TEST_SWITCH,
+                RequiredConfig::PASSWORD,
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            ..Configuration::default()
+        };
+
+        let required: Vec<&str> = description
+            .required_options()
+            .iter()
+            .map(|option| option.name)
+            .collect();
+        let optional: Vec<&str> = description
+            .optional_options()
+            .iter()
+            .map(|option| option.name)
+            .collect();
+        assert_eq!(required, vec!["password", "user"]);
+        assert_eq!(optional, vec!["testparam", "testswitch"]);
+    }
+
+    // Test that an option can't be used without the options it requires
+    #[test]
+    fn option_requires_other_option() {
+        struct KeystoreConfig;
+
+        impl Configurable for KeystoreConfig {
+            fn get_config_description() -> Configuration {
+                Configuration {
+                    options: [
+                        ConfigOption {
+                            name: "tls-keystore-location",
+                            takes_argument: true,
+                            requires: &["tls-keystore-password"],
+                            ..ConfigOption::DEFAULT
+                        },
+                        ConfigOption {
+                            name: "tls-keystore-password",
+                            takes_argument: true,
+                            secret: true,
+                            ..ConfigOption::DEFAULT
+                        },
+                    ]
+                    .iter()
+                    .cloned()
+                    .collect(),
+                    name: "Test Tool",
+                    version: "0.1",
+                    ..Configuration::default()
+                }
+            }
+
+            fn parse_values(
+                _: HashMap<ConfigOption, Option<Vec<String>>>,
+            ) -> Result<Self, anyhow::Error> {
+                Ok(KeystoreConfig)
+            }
+        }
+
+        let env_var_name = get_and_delete_env_var();
+
+        let result: Result<KeystoreConfig, ConfigError> = ConfigBuilder::build(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--tls-keystore-location"),
+                OsString::from("/keystore.p12"),
+            ],
+            &env_var_name,
+        );
+        match result {
+            Err(ConfigError::InvalidArguments(err)) => {
+                assert!(err.message.contains("--tls-keystore-password"))
+            }
+            _ => panic!("missing requirement was not rejected"),
+        }
+
+        let result: Result<KeystoreConfig, ConfigError> = ConfigBuilder::build(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--tls-keystore-location"),
+                OsString::from("/keystore.p12"),
+                OsString::from("--tls-keystore-password"),
+                OsString::from("changeit"),
+            ],
+            &env_var_name,
+        );
+        assert!(result.is_ok());
+    }
+
+    // Test that every entry of a file becomes a value of a list_from_file option
+    #[test]
+    fn list_from_file() {
+        const ALLOW: ConfigOption = ConfigOption {
+            name: "allow",
+            takes_argument: true,
+            list: true,
+            list_from_file: true,
+            ..ConfigOption::DEFAULT
+        };
+        let description = Configuration {
+            options: [ALLOW].iter().cloned().collect(),
+            name: "Test Tool",
+            version: "0.1",
+            ..Configuration::default()
+        };
+
+        let env_var_name = get_and_delete_env_var();
+        let list_file = env::temp_dir().join(format!("{}-allowlist", env_var_name));
+        fs::write(
+            &list_file,
+            "# Internal networks\n10.0.0.0/8, 172.16.0.0/12\n\n  192.168.0.0/16 # office\n,\n",
+        )
+        .expect("Error writing list file");
+
+        let resolved = ConfigBuilder::resolve(
+            &description,
+            vec![
+                OsString::from("filename"),
+                OsString::from("--allow=127.0.0.1/32"),
+                OsString::from(format!("--allow=@{}", list_file.display())),
+            ],
+            &env_var_name,
+            &mut NonInteractive,
+            false,
+        );
+        fs::remove_file(&list_file).expect("Error removing list file");
+        assert_eq!(
+            resolved.expect("Error resolving values").values.get(&ALLOW),
+            Some(&Some(vec![
+                String::from("127.0.0.1/32"),
+                String::from("10.0.0.0/8"),
+                String::from("172.16.0.0/12"),
+                String::from("192.168.0.0/16"),
+            ]))
+        );
+    }
+
+    // Test that values of the form @<file> are replaced by the content of the file
+    #[test]
+    fn value_from_file() {
+        let env_var_name = get_and_delete_env_var();
+        let value_file = env::temp_dir().join(format!("{}-value", env_var_name));
+        fs::write(&value_file, "  fromvaluefile\n").expect("Error writing value file");
+
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from(format!("--testparam=@{}", value_file.display())),
+            OsString::from("--testparam2=@@literal"),
+        ];
+        let config: TestConfig = ConfigBuilder::build(command_line_args, &env_var_name)
+            .expect("Error building config object!");
+        assert_eq!(
+            config.get_first_and_only_value(&TestConfig::TEST_PARAM),
+            "fromvaluefile"
+        );
+        assert_eq!(
+            config.get_first_and_only_value(&TestConfig::TEST_PARAM2),
+            "@literal"
+        );
+
+        fs::remove_file(&value_file).expect("Error removing value file");
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from(format!("--testparam=@{}", value_file.display())),
+        ];
+        let result: Result<TestConfig, ConfigError> =
+            ConfigBuilder::build(command_line_args, &env_var_name);
+        assert!(matches!(
+            result,
+            Err(ConfigError::ValueFileUnreadable { name, path, .. })
+                if name == "testparam" && path == value_file
+        ));
+    }
+
+    // Test that values which start with `@` but are not meant as a file have to be escaped,
+    // no matter where they come from
+    #[test]
+    fn escaped_at_sign() {
+        const PREFIX: &str = "ESCAPED_AT_SIGN_TEST";
+        let env_var_name = get_and_delete_env_var();
+        let description = Configuration {
+            env_prefix: Some(PREFIX),
+            ..TestConfig::get_config_description()
+        };
+        let resolve = |args: &[&str]| {
+            let mut commandline = vec![OsString::from("filename")];
+            commandline.extend(args.iter().map(OsString::from));
+            ConfigBuilder::resolve(
+                &description,
+                commandline,
+                &env_var_name,
+                &mut NonInteractive,
+                false,
+            )
+        };
+
+        assert!(matches!(
+            resolve(&["--testparam=@daily"]),
+            Err(ConfigError::ValueFileUnreadable { name, .. }) if name == "testparam"
+        ));
+        let resolved = resolve(&["--testparam=@@daily"]).expect("Error resolving values");
+        assert_eq!(
+            resolved.values.first_value(&TestConfig::TEST_PARAM),
+            Some("@daily")
+        );
+
+        env::set_var("ESCAPED_AT_SIGN_TEST_TESTPARAM2", "@@ops-channel");
+        let resolved = resolve(&[]).expect("Error resolving values");
+        assert_eq!(
+            resolved.values.first_value(&TestConfig::TEST_PARAM2),
+            Some("@ops-channel")
+        );
+        env::remove_var("ESCAPED_AT_SIGN_TEST_TESTPARAM2");
+    }
+
+    // Test that the rendered command line resolves to the same values again
+    #[test]
+    fn command_line_round_trip() {
+        let env_var_name = get_and_delete_env_var();
+        env::set_var(
+            &env_var_name,
+            get_absolute_file("resources/test/config1.conf"),
+        );
+        let description = TestConfig::get_config_description();
+
+        let resolved = ConfigBuilder::resolve(
+            &description,
+            vec![
+                OsString::from("filename"),
+                OsString::from("--testswitch"),
+                OsString::from("--testmultiple=@@at"),
+                OsString::from("--testmultiple=-1"),
+            ],
+            &env_var_name,
+            &mut NonInteractive,
+            false,
+        )
+        .expect("Error resolving values");
+
+        let mut command_line = vec![OsString::from("filename")];
+        command_line.extend(resolved.values.to_command_line(false));
+        assert_eq!(
+            command_line,
+            vec![
+                OsString::from("filename"),
+                OsString::from("--testmultiple=@@at"),
+                OsString::from("--testmultiple=-1"),
+                OsString::from("--testparam=fromfile"),
+                OsString::from("--testparam2=fromfile2"),
+                OsString::from("--testswitch"),
+            ]
+        );
+
+        let round_trip = ConfigBuilder::resolve(
+            &description,
+            command_line,
+            &get_and_delete_env_var(),
+            &mut NonInteractive,
+            false,
+        )
+        .expect("Error resolving values");
+        // Only the values are compared, the round trip does not read the config file again
+        assert_eq!(*round_trip.values, *resolved.values);
+
+        // A cleared list stays cleared and a default with placeholders is left out
+        let description = Configuration {
+            options: [
+                TestConfig::TEST_MULTIPLE,
+                ConfigOption {
+                    name: "data-dir",
+                    takes_argument: true,
+                    default: Some("/var/lib/{name}"),
+                    ..ConfigOption::DEFAULT
+                },
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            ..TestConfig::get_config_description()
+        };
+        let resolve = |command_line: Vec<OsString>| {
+            ConfigBuilder::resolve(
+                &description,
+                command_line,
+                &get_and_delete_env_var(),
+                &mut NonInteractive,
+                false,
+            )
+            .expect("Error resolving values")
+        };
+        let resolved = resolve(vec![
+            OsString::from("filename"),
+            OsString::from("--testmultiple="),
+        ]);
+        assert_eq!(resolved.values.get(&TestConfig::TEST_MULTIPLE), Some(&None));
+
+        let mut command_line = vec![OsString::from("filename")];
+        command_line.extend(resolved.values.to_command_line(false));
+        assert_eq!(
+            command_line,
+            vec![
+                OsString::from("filename"),
+                OsString::from("--testmultiple=")
+            ]
+        );
+        assert_eq!(*resolve(command_line).values, *resolved.values);
+    }
+
+    // Test that placeholders in default values are replaced
+    #[test]
+    fn default_with_placeholders() {
+        const DATA_DIR: ConfigOption = ConfigOption {
+            name: "data-dir",
+            default: Some("/var/lib/{name}/{version}/data"),
+            takes_argument: true,
+            ..ConfigOption::DEFAULT
+        };
+        let description = Configuration {
+            name: "testtool",
+            version: "0.1",
+            options: [DATA_DIR].iter().cloned().collect(),
+            ..Configuration::default()
+        };
+
+        let resolved = ConfigBuilder::resolve(
+            &description,
+            vec![OsString::from("filename")],
+            &get_and_delete_env_var(),
+            &mut NonInteractive,
+            false,
+        )
+        .expect("Error resolving values");
+        assert_eq!(
+            resolved.values.get(&DATA_DIR),
+            Some(&Some(vec![String::from("/var/lib/testtool/0.1/data")]))
+        );
+
+        // Values that were specified are left alone
+        let resolved = ConfigBuilder::resolve(
+            &description,
+            vec![
+                OsString::from("filename"),
+                OsString::from("--data-dir=/{name}"),
+            ],
+            &get_and_delete_env_var(),
+            &mut NonInteractive,
+            false,
+        )
+        .expect("Error resolving values");
+        assert_eq!(
+            resolved.values.get(&DATA_DIR),
+            Some(&Some(vec![String::from("/{name}")]))
+        );
+    }
+
+    // Test that reading a value from a named pipe without a writer times out
+    #[test]
+    #[cfg(unix)]
+    fn value_from_fifo_timeout() {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let env_var_name = get_and_delete_env_var();
+        let fifo = env::temp_dir().join(format!("{}-fifo", env_var_name));
+        let c_path = CString::new(fifo.as_os_str().as_bytes()).expect("Path contains NUL");
+        // SAFETY: c_path is a valid NUL terminated string
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+
+        let description = Configuration {
+            fifo_timeout: Some(Duration::from_millis(100)),
+            ..TestConfig::get_config_description()
+        };
+        let result = ConfigBuilder::resolve(
+            &description,
+            vec![
+                OsString::from("filename"),
+                OsString::from(format!("--testparam=@{}", fifo.display())),
+            ],
+            &env_var_name,
+            &mut NonInteractive,
+            false,
+        );
+        assert!(matches!(
+            result,
+            Err(ConfigError::SecretSourceTimeout { name, path })
+                if name == "testparam" && path == fifo
+        ));
+        fs::remove_file(fifo).expect("Error removing pipe");
+    }
+
+    // Test that relative paths are resolved against the base directory
+    #[test]
+    fn absolute_path() {
+        const LOG_DIR: ConfigOption = ConfigOption {
+            name: "log-dir",
+            takes_argument: true,
+            list: true,
+            absolute_path: true,
+            ..ConfigOption::DEFAULT
+        };
+        let base_dir = env::temp_dir().join("base");
+        let description = Configuration {
+            options: [LOG_DIR].iter().cloned().collect(),
+            base_dir: Some(base_dir.clone()),
+            name: "Test Tool",
+            version: "0.1",
+            ..Configuration::default()
+        };
+
+        let resolved = ConfigBuilder::resolve(
+            &description,
+            vec![
+                OsString::from("filename"),
+                OsString::from("--log-dir"),
+                OsString::from("logs/app"),
+                OsString::from("--log-dir"),
+                OsString::from("/var/log"),
+            ],
+            &get_and_delete_env_var(),
+            &mut NonInteractive,
+            false,
+        )
+        .expect("Error resolving values");
+
+        let paths: Vec<PathBuf> = resolved
+            .values
+            .get_os_values(&LOG_DIR)
+            .expect("No values for log-dir")
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![base_dir.join("logs/app"), PathBuf::from("/var/log")]
+        );
+        assert!(paths.iter().all(|path| path.is_absolute()));
+    }
+
+    // Test that the path kind of an option is checked after parsing
+    #[test]
+    fn check_path_kind() {
+        struct PathKindConfig;
+
+        impl Configurable for PathKindConfig {
+            fn get_config_description() -> Configuration {
+                Configuration {
+                    options: [ConfigOption {
+                        name: "config-dir",
+                        takes_argument: true,
+                        path_kind: Some(PathKind::ExistingDir),
+                        ..ConfigOption::DEFAULT
+                    }]
+                    .iter()
+                    .cloned()
+                    .collect(),
+                    name: "Test Tool",
+                    version: "0.1",
+                    ..Configuration::default()
+                }
+            }
+
+            fn parse_values(
+                _: HashMap<ConfigOption, Option<Vec<String>>>,
+            ) -> Result<Self, anyhow::Error> {
+                Ok(PathKindConfig)
+            }
+        }
+
+        let env_var_name = get_and_delete_env_var();
+        let dir = env::temp_dir();
+        let missing = dir.join(format!("{}-missing", env_var_name));
+
+        let result: Result<PathKindConfig, ConfigError> = ConfigBuilder::build(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--config-dir"),
+                dir.into_os_string(),
+            ],
+            &env_var_name,
+        );
+        assert!(result.is_ok());
+
+        let result: Result<PathKindConfig, ConfigError> = ConfigBuilder::build(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--config-dir"),
+                missing.clone().into_os_string(),
+            ],
+            &env_var_name,
+        );
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidPath { name, path, expected: PathKind::ExistingDir })
+                if name == "config-dir" && path == missing
+        ));
+    }
+
+    // Test that reloading picks up changes in the config file, while values from the command
+    // line stay fixed
+    #[test]
+    fn reload_changed_file() {
+        let env_var_name = get_and_delete_env_var();
+        let config_file = env::temp_dir().join(format!("{}.conf", env_var_name));
+        fs::write(&config_file, "--testparam=fromfile\n--testparam2=before\n")
+            .expect("Error writing config file");
+        env::set_var(&env_var_name, &config_file);
+
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--testparam"),
+            OsString::from("param1"),
+        ];
+        let (config, mut reloader): (TestConfig, _) =
+            ConfigBuilder::build_reloadable(command_line_args, &env_var_name)
+                .expect("Error building config object!");
+        assert_eq!(
+            config.get_first_and_only_value(&TestConfig::TEST_PARAM2),
+            "before"
+        );
+
+        fs::write(&config_file, "--testparam=changed\n--testparam2=after\n")
+            .expect("Error writing config file");
+        let (config, changed): (TestConfig, _) =
+            reloader.reload().expect("Error reloading config object!");
+        assert_eq!(changed, vec![TestConfig::TEST_PARAM2]);
+        assert_eq!(
+            config.get_first_and_only_value(&TestConfig::TEST_PARAM2),
+            "after"
+        );
+        assert_eq!(
+            config.get_first_and_only_value(&TestConfig::TEST_PARAM),
+            "param1"
+        );
+
+        // Nothing changed since the last reload
+        let (_, changed): (TestConfig, _) =
+            reloader.reload().expect("Error reloading config object!");
+        assert!(changed.is_empty());
+
+        fs::remove_file(&config_file).expect("Error removing config file");
+    }
+
+    // Test that values the user was prompted for are kept as options when reloading, even if
+    // the command line has trailing arguments
+    #[test]
+    fn reload_prompted_with_trailing_args() {
+        struct TrailingConfig {
+            values: ResolvedValues,
+        }
+
+        impl Configurable for TrailingConfig {
+            fn get_config_description() -> Configuration {
+                Configuration {
+                    trailing_args: true,
+                    ..TestConfig::get_config_description()
+                }
+            }
+
+            fn parse_values(
+                _: HashMap<ConfigOption, Option<Vec<String>>>,
+            ) -> Result<Self, anyhow::Error> {
+                unreachable!("parse_resolved is overridden")
+            }
+
+            // The trailing arguments are only part of `ResolvedValues`
+            fn parse_resolved(values: ResolvedValues) -> Result<Self, anyhow::Error> {
+                Ok(TrailingConfig { values })
+            }
+        }
+
+        let env_var_name = get_and_delete_env_var();
+        let mut reloader = ConfigReloader::new(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--testparam=param1"),
+                OsString::from("--"),
+                OsString::from("child"),
+            ],
+            vec![OsString::from("--testparam2"), OsString::from("prompted")],
+            &env_var_name,
+            ResolvedValues::default(),
+        );
+
+        let (config, _): (TrailingConfig, _) =
+            reloader.reload().expect("Error reloading config object!");
+        assert_eq!(
+            config.values.get(&TestConfig::TEST_PARAM2),
+            Some(&Some(vec![String::from("prompted")]))
+        );
+        assert_eq!(config.values.trailing_args(), &[OsString::from("child")]);
+    }
+
+    // Test that unknown arguments are collected in partial mode while known ones are parsed
+    #[test]
+    fn parse_partial() {
+        let env_var_name = get_and_delete_env_var();
+
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--plugin-flag"),
+            OsString::from("--testparam"),
+            OsString::from("param1"),
+            OsString::from("--plugin-option"),
+            OsString::from("plugin-value"),
+            OsString::from("--testparam2=param2"),
+            OsString::from("--testswitch"),
+            OsString::from("-p"),
+        ];
+        let (config, unknown): (TestConfig, _) =
+            ConfigBuilder::build_partial(command_line_args, &env_var_name)
+                .expect("Error building config object!");
+
+        assert!(config.argument_was_provided(&TestConfig::TEST_SWITCH));
+        assert_eq!(
+            config.get_first_and_only_value(&TestConfig::TEST_PARAM),
+            "param1"
+        );
+        assert_eq!(
+            config.get_first_and_only_value(&TestConfig::TEST_PARAM2),
+            "param2"
+        );
+        assert_eq!(
+            unknown,
+            vec![
+                OsString::from("--plugin-flag"),
+                OsString::from("--plugin-option"),
+                OsString::from("plugin-value"),
+                OsString::from("-p"),
+            ]
+        );
+    }
+
+    // Without partial mode unknown arguments are an error
+    #[test]
+    fn unknown_argument_fails() {
+        let env_var_name = get_and_delete_env_var();
+
+        let command_line_args: Vec<OsString> =
+            vec![OsString::from("filename"), OsString::from("--plugin-flag")];
+        let result: Result<TestConfig, ConfigError> =
+            ConfigBuilder::build(command_line_args, &env_var_name);
+        assert!(matches!(result, Err(ConfigError::InvalidArguments(_))));
+    }
+
+    #[test]
+    fn strict_args() {
+        let env_var_name = get_and_delete_env_var();
+        let command_line_args = vec![
+            OsString::from("filename"),
+            OsString::from("--testparam=param1"),
+            OsString::from("--plugin-flag"),
+        ];
+
+        let lenient = TestConfig::get_config_description();
+        let resolved = ConfigBuilder::resolve(
+            &lenient,
+            command_line_args.clone(),
+            &env_var_name,
+            &mut NonInteractive,
+            true,
+        )
+        .expect("Error resolving values");
+        assert_eq!(resolved.unknown, vec![OsString::from("--plugin-flag")]);
+
+        let strict = Configuration {
+            strict_args: true,
+            ..TestConfig::get_config_description()
+        };
+        let result = ConfigBuilder::resolve(
+            &strict,
+            command_line_args,
+            &env_var_name,
+            &mut NonInteractive,
+            true,
+        );
+        assert!(matches!(result, Err(ConfigError::InvalidArguments(_))));
+    }
+
+    #[test]
+    fn required_one_of() {
+        let env_var_name = get_and_delete_env_var();
+        let description = Configuration {
+            required_one_of: vec![OptionGroup {
+                name: "mode",
+                options: &["testparam2", "testswitch"],
+            }],
+            ..TestConfig::get_config_description()
+        };
+        let resolve = |args: &[&str]| {
+            let mut commandline = vec![OsString::from("filename")];
+            commandline.extend(args.iter().map(OsString::from));
+            ConfigBuilder::resolve(
+                &description,
+                commandline,
+                &env_var_name,
+                &mut NonInteractive,
+                false,
+            )
+        };
+
+        assert!(matches!(
+            resolve(&[]),
+            Err(ConfigError::InvalidArguments(_))
+        ));
+
+        assert!(resolve(&["--testswitch"]).is_ok());
+
+        assert!(matches!(
+            resolve(&["--testswitch", "--testparam2=param2"]),
+            Err(ConfigError::InvalidArguments(_))
+        ));
+    }
+
+    #[test]
+    fn check_address() {
+        let env_var_name = get_and_delete_env_var();
+        let description = Configuration {
+            options: [ConfigOption {
+                name: "listen-address",
+                takes_argument: true,
+                address: Some(AddressKind::Socket),
+                ..ConfigOption::DEFAULT
+            }]
+            .iter()
+            .cloned()
+            .collect(),
+            name: "Test Tool",
+            version: "0.1",
+            ..Configuration::default()
+        };
+        let resolve = |address: &str| {
+            ConfigBuilder::resolve(
+                &description,
+                vec![
+                    OsString::from("filename"),
+                    OsString::from(format!("--listen-address={}", address)),
+                ],
+                &env_var_name,
+                &mut NonInteractive,
+                false,
+            )
+        };
+
+        assert!(resolve("127.0.0.1:9000").is_ok());
+        assert!(matches!(
+            resolve("127.0.0.1"),
+            Err(ConfigError::InvalidArguments(err)) if err.message.contains("not a socket address")
+        ));
+        assert!(matches!(
+            resolve("example.invalid:9000"),
+            Err(ConfigError::InvalidArguments(_))
+        ));
+    }
+
+    // Test that all checks of an option run, clap only keeps the last validator
+    #[test]
+    fn combined_validators() {
+        let env_var_name = get_and_delete_env_var();
+        let resolve = |option: ConfigOption, value: &str| {
+            let description = Configuration {
+                options: [option.clone()].iter().cloned().collect(),
+                ..TestConfig::get_config_description()
+            };
+            ConfigBuilder::resolve(
+                &description,
+                vec![
+                    OsString::from("filename"),
+                    OsString::from(format!("--{}={}", option.name, value)),
+                ],
+                &env_var_name,
+                &mut NonInteractive,
+                false,
+            )
+        };
+
+        // Valid base64, but not an address
+        let address_base64 = ConfigOption {
+            name: "address",
+            takes_argument: true,
+            address: Some(AddressKind::Ip),
+            base64: true,
+            ..ConfigOption::DEFAULT
+        };
+        assert!(matches!(
+            resolve(address_base64.clone(), "AAAA"),
+            Err(ConfigError::InvalidArguments(_))
+        ));
+        assert!(matches!(
+            resolve(address_base64, "127.0.0.1"),
+            Err(ConfigError::InvalidArguments(err)) if err.message.contains("base64")
+        ));
+
+        // A valid URL, but not an address
+        let address_url = ConfigOption {
+            name: "address",
+            takes_argument: true,
+            address: Some(AddressKind::Socket),
+            url_schemes: &["https"],
+            ..ConfigOption::DEFAULT
+        };
+        assert!(matches!(
+            resolve(address_url.clone(), "https://example.com:443"),
+            Err(ConfigError::InvalidArguments(err)) if err.message.contains("not a socket address")
+        ));
+        assert!(matches!(
+            resolve(address_url, "127.0.0.1:443"),
+            Err(ConfigError::InvalidArguments(err)) if err.message.contains("not a valid URL")
+        ));
+    }
+
+    #[test]
+    fn config_file_used() {
+        let env_var_name = get_and_delete_env_var();
+        let description = TestConfig::get_config_description();
+        let resolve = |args: &[&str]| {
+            let mut commandline = vec![OsString::from("filename")];
+            commandline.extend(args.iter().map(OsString::from));
+            ConfigBuilder::resolve(
+                &description,
+                commandline,
+                &env_var_name,
+                &mut NonInteractive,
+                false,
+            )
+            .expect("Error resolving values")
+            .values
+        };
+
+        assert_eq!(resolve(&[]).config_file_used(), None);
+
+        let missing = env::temp_dir().join(format!("{}-missing.conf", env_var_name));
+        env::set_var(&env_var_name, &missing);
+        assert_eq!(resolve(&[]).config_file_used(), None);
+
+        let config_file = PathBuf::from(get_absolute_file("resources/test/config1.conf"));
+        env::set_var(&env_var_name, &config_file);
+        assert_eq!(resolve(&[]).config_file_used(), Some(config_file.as_path()));
+        assert_eq!(resolve(&["--no-config"]).config_file_used(), None);
+    }
+
+    // Test that lines of the config file that can't be parsed are reported, while all other
+    // lines are used
+    #[test]
+    fn config_file_warnings() {
+        let env_var_name = get_and_delete_env_var();
+        let config_file = PathBuf::from(get_absolute_file("resources/test/config_warning.conf"));
+        env::set_var(&env_var_name, &config_file);
+
+        let values = ConfigBuilder::resolve(
+            &TestConfig::get_config_description(),
+            vec![OsString::from("filename")],
+            &env_var_name,
+            &mut NonInteractive,
+            false,
+        )
+        .expect("Error resolving values")
+        .values;
+        assert_eq!(
+            values.get(&TestConfig::TEST_PARAM),
+            Some(&Some(vec![String::from("fromfile")]))
+        );
+        assert_eq!(values.get(&TestConfig::TEST_SWITCH), Some(&Some(vec![])));
+        assert_eq!(
+            values.warnings(),
+            &[ConfigWarning {
+                file: config_file,
+                line: 4,
+                message: String::from("heredoc is not terminated by 'END'"),
+            }]
+        );
+    }
+
+    #[test]
+    fn build_from_reader() {
+        let content = io::Cursor::new("--testparam=fromreader\n--testparam2=fromreader2\n");
+        let config: TestConfig = ConfigBuilder::build_from_reader(
+            vec![
+                OsString::from("filename"),
+                OsString::from("--testparam2=fromcommandline"),
+            ],
+            content,
+        )
+        .expect("Error building config object!");
+
+        assert_eq!(
+            config.get_first_and_only_value(&TestConfig::TEST_PARAM),
+            "fromreader"
+        );
+        assert_eq!(
+            config.get_first_and_only_value(&TestConfig::TEST_PARAM2),
+            "fromcommandline"
+        );
+
+        let result: Result<TestConfig, ConfigError> = ConfigBuilder::build_from_reader(
+            vec![OsString::from("filename")],
+            io::Cursor::new("--cert <<END\nunterminated\n"),
+        );
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidConfigContent { .. })
+        ));
+    }
+
+    #[test]
+    fn trailing_args() {
+        let env_var_name = get_and_delete_env_var();
+        let description = Configuration {
+            trailing_args: true,
+            ..TestConfig::get_config_description()
+        };
+
+        let resolved = ConfigBuilder::resolve(
+            &description,
+            vec![
+                OsString::from("filename"),
+                OsString::from("--testparam"),
+                OsString::from("param1"),
+                OsString::from("--testswitch"),
+                OsString::from("--"),
+                OsString::from("child"),
+                OsString::from("--child-flag"),
+                OsString::from("--testparam=child"),
+                OsString::from("--"),
+            ],
+            &env_var_name,
+            &mut NonInteractive,
+            false,
+        )
+        .expect("Error resolving values");
+
+        assert_eq!(
+            resolved.values.get(&TestConfig::TEST_PARAM),
+            Some(&Some(vec![String::from("param1")]))
+        );
+        assert_eq!(
+            resolved.values.get(&TestConfig::TEST_SWITCH),
+            Some(&Some(vec![]))
+        );
+        assert_eq!(
+            resolved.values.trailing_args(),
+            &[
+                OsString::from("child"),
+                OsString::from("--child-flag"),
+                OsString::from("--testparam=child"),
+                OsString::from("--"),
+            ]
+        );
+    }
+
+    #[test]
+    fn interpolate_config_file() {
+        let content = "--testparam=/data\n--testparam2=${testparam}/logs\n";
+        let config: TestConfig = ConfigBuilder::build_from_reader(
+            vec![OsString::from("filename")],
+            io::Cursor::new(content),
+        )
+        .expect("Error building config object!");
+        assert_eq!(
+            config.get_first_and_only_value(&TestConfig::TEST_PARAM2),
+            "${testparam}/logs"
+        );
+
+        let description = Configuration {
+            interpolate_config_file: true,
+            ..TestConfig::get_config_description()
+        };
+        let config_args =
+            parse_config_reader(io::Cursor::new(content)).expect("Error parsing config");
+        let resolved = ConfigBuilder::resolve_from(
+            &description,
+            vec![OsString::from("filename")],
+            ConfigSource::Args(&config_args),
+            &mut NonInteractive,
+            false,
+        )
+        .expect("Error resolving values");
+        assert_eq!(
+            resolved.values.get(&TestConfig::TEST_PARAM2),
+            Some(&Some(vec![String::from("/data/logs")]))
+        );
+    }
+
+    #[test]
+    fn defaults_file() {
+        let env_var_name = get_and_delete_env_var();
+        let defaults_file = env::temp_dir().join(format!("{}-defaults.conf", env_var_name));
+        fs::write(
+            &defaults_file,
+            "--testparam=fromdefaults\n--testparam2=fromdefaults2\n",
+        )
+        .expect("Error writing defaults file");
+
+        let description = Configuration {
+            defaults_file: Some(defaults_file.clone()),
+            ..TestConfig::get_config_description()
+        };
+        let resolve = || {
+            ConfigBuilder::resolve(
+                &description,
+                vec![OsString::from("filename")],
+                &env_var_name,
+                &mut NonInteractive,
+                false,
+            )
+            .expect("Error resolving values")
+            .values
+        };
+
+        let values = resolve();
+        assert_eq!(
+            values.get(&TestConfig::TEST_PARAM),
+            Some(&Some(vec![String::from("fromdefaults")]))
+        );
+        assert_eq!(
+            values.get(&TestConfig::TEST_PARAM2),
+            Some(&Some(vec![String::from("fromdefaults2")]))
+        );
+
+        // config1.conf sets both options
+        env::set_var(
+            &env_var_name,
+            get_absolute_file("resources/test/config1.conf"),
+        );
+        let values = resolve();
+        assert_eq!(
+            values.get(&TestConfig::TEST_PARAM),
+            Some(&Some(vec![String::from("fromfile")]))
+        );
+        assert_eq!(
+            values.get(&TestConfig::TEST_PARAM2),
+            Some(&Some(vec![String::from("fromfile2")]))
+        );
+
+        // A missing defaults file is ignored
+        fs::remove_file(&defaults_file).expect("Error removing defaults file");
+        env::remove_var(&env_var_name);
+        let values = resolve();
+        assert_eq!(
+            values.get(&TestConfig::TEST_PARAM),
+            Some(&Some(vec![String::from("udtarine")]))
+        );
+    }
+
+    /// Helper function to convert a filename that is relative to the config crate Cargo.toml
+    /// file to an absolute path by retrieving the CARGO_MANIFEST_DIR environment variable
+    /// and prepending this to the filename
+    ///
+    /// * `filename` - A relative filename (no leading /)
+    fn get_absolute_file(filename: &str) -> String {
+        env!("CARGO_MANIFEST_DIR").to_owned() + "/" + filename
+    }
+
+    #[test]
+    fn config_option_has_default() {
+        let default = ConfigOption::default();
+        assert_eq!(
+            default,
+            ConfigOption {
+                name: "",
+                default: None,
+                required: false,
+                takes_argument: false,
+                help: "",
+                documentation: "",
+                example: None,
+                list: false,
+                secret: false,
+                deprecated: None,
+                deprecated_since: None,
+                experimental: false,
+                path: false,
+                path_kind: None,
+                requires: &[],
+                absolute_path: false,
+                dedup_list: false,
+                address: None,
+                possible_values: &[],
+                base64: false,
+                non_empty: false,
+                url_schemes: &[],
+                list_from_file: false,
+            }
+        )
+    }
+
+    #[test]
+    fn deprecation_warning() {
+        let option = ConfigOption {
+            name: "foo",
+            deprecated: Some("use 'bar'"),
+            deprecated_since: Some("1.2.0"),
+            ..ConfigOption::DEFAULT
+        };
+        assert_eq!(
+            option.deprecation_warning(),
+            Some(String::from("'foo' deprecated since 1.2.0; use 'bar'"))
+        );
+
+        let option = ConfigOption {
+            deprecated_since: None,
+            ..option
+        };
+        assert_eq!(
+            option.deprecation_warning(),
+            Some(String::from("'foo' deprecated; use 'bar'"))
+        );
+
+        assert_eq!(TestConfig::TEST_PARAM.deprecation_warning(), None);
+    }
+
+    // Configuration with required options that is used to test the interactive mode
+    struct RequiredConfig {
+        values: HashMap<ConfigOption, Option<Vec<String>>>,
+    }
+
+    impl RequiredConfig {
+        pub const USER: ConfigOption = ConfigOption {
+            name: "user",
+            required: true,
+            takes_argument: true,
+            help: "The user to connect as",
+            ..ConfigOption::DEFAULT
+        };
+        pub const PASSWORD: ConfigOption = ConfigOption {
+            name: "password",
+            required: true,
+            takes_argument: true,
+            help: "The password of the user",
+            secret: true,
+            ..ConfigOption::DEFAULT
+        };
+    }
+
+    impl Configurable for RequiredConfig {
+        fn get_config_description() -> Configuration {
+            Configuration {
+                name: "Test Tool",
+                version: "0.1",
+                about: "blabla",
+                options: [RequiredConfig::USER, RequiredConfig::PASSWORD]
+                    .iter()
+                    .cloned()
+                    .collect(),
+                interactive: true,
+                ..Configuration::default()
+            }
+        }
+
+        fn parse_values(
+            parsed_values: HashMap<ConfigOption, Option<Vec<String>>>,
+        ) -> Result<Self, anyhow::Error> {
+            Ok(RequiredConfig {
+                values: parsed_values,
+            })
+        }
+    }
+
+    // Stands in for a terminal, answers with fixed values and records what was asked
+    struct FakePrompt {
+        interactive: bool,
+        answers: HashMap<&'static str, &'static str>,
+        // Name of the option and whether it was secret for every prompt
+        asked: Vec<(&'static str, bool)>,
+    }
+
+    impl Prompt for FakePrompt {
+        fn is_interactive(&self) -> bool {
+            self.interactive
+        }
+
+        fn read_value(&mut self, option: &ConfigOption) -> io::Result<String> {
+            self.asked.push((option.name, option.secret));
+            Ok(self.answers[option.name].to_string())
+        }
+    }
+
+    #[test]
+    fn prompt_for_missing_required() {
+        let env_var_name = get_and_delete_env_var();
+        let mut prompt = FakePrompt {
+            interactive: true,
+            answers: [("password", "secret")].iter().cloned().collect(),
+            asked: vec![],
+        };
+
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--user"),
+            OsString::from("admin"),
+        ];
+        let config: RequiredConfig =
+            ConfigBuilder::build_with_prompt(command_line_args, &env_var_name, &mut prompt, false)
+                .map(|(outcome, _)| ConfigBuilder::exit_unless_config(outcome))
+                .expect("Error building config object!");
+
+        // Only the missing option should have been prompted for and without echo
+        assert_eq!(prompt.asked, vec![("password", true)]);
+        assert_eq!(
+            config.values.get(&RequiredConfig::PASSWORD),
+            Some(&Some(vec![String::from("secret")]))
+        );
+        assert_eq!(
+            config.values.get(&RequiredConfig::USER),
+            Some(&Some(vec![String::from("admin")]))
+        );
+    }
+
+    #[test]
+    fn missing_required_without_terminal() {
+        let env_var_name = get_and_delete_env_var();
+        let mut prompt = FakePrompt {
+            interactive: false,
+            answers: HashMap::new(),
+            asked: vec![],
+        };
+
+        let command_line_args: Vec<OsString> = vec![
+            OsString::from("filename"),
+            OsString::from("--user"),
+            OsString::from("admin"),
+        ];
+        let result: Result<(ConfigOutcome<RequiredConfig>, _), ConfigError> =
+            ConfigBuilder::build_with_prompt(command_line_args, &env_var_name, &mut prompt, false);
+
+        assert!(prompt.asked.is_empty());
+        assert!(matches!(
+            result,
+            Err(ConfigError::MissingRequired { name }) if name == "password"
+        ));
     }
 }
@@ -0,0 +1,275 @@
+//! This module contains checks for mistakes in the definition of a configuration that clap
+//! either silently accepts or only reports at runtime.
+//! The intended use is to call `Configuration::lint` (or `Configuration::lint_options`)
+//! from a unit test of the crate that defines the configuration.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use crate::{ConfigError, ConfigOption, Configuration, RESERVED_FLAGS};
+
+/// A problem found in the definition of a configuration
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConfigLint {
+    /// More than one option uses the same name, all but one of them will be silently dropped
+    /// when the options are collected into the `HashSet` of a `Configuration`
+    DuplicateName { name: &'static str, count: usize },
+    /// An option has an empty name and can't be specified on the command line
+    EmptyName,
+    /// An option has no help text
+    EmptyHelp { name: &'static str },
+    /// An option is required but also has a default, so it can never be missing
+    RequiredWithDefault { name: &'static str },
+}
+
+impl ConfigLint {
+    /// Whether this lint is an actual error in the definition or just a warning
+    pub fn is_error(&self) -> bool {
+        !matches!(self, ConfigLint::EmptyHelp { .. })
+    }
+}
+
+impl Display for ConfigLint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigLint::DuplicateName { name, count } => {
+                write!(f, "option '{}' is defined {} times", name, count)
+            }
+            ConfigLint::EmptyName => write!(f, "option with an empty name"),
+            ConfigLint::EmptyHelp { name } => write!(f, "option '{}' has no help text", name),
+            ConfigLint::RequiredWithDefault { name } => write!(
+                f,
+                "option '{}' is required but has a default value, so it can never be missing",
+                name
+            ),
+        }
+    }
+}
+
+impl Configuration {
+    /// Checks the options of this configuration for common mistakes
+    ///
+    /// Duplicate option names can not be detected here, because the duplicates have already
+    /// been removed when the options were collected into the set, use `lint_options` on the
+    /// list of options before collecting them for that
+    pub fn lint(&self) -> Vec<ConfigLint> {
+        let mut options: Vec<&ConfigOption> = self.options.iter().collect();
+        options.sort();
+        options.into_iter().flat_map(lint_option).collect()
+    }
+
+    /// Checks that the name and version of the application are set, clap renders a broken
+    /// `--help` and `--version` otherwise, that all option names referred to by `requires`
+    /// and `required_one_of` exist, clap panics or silently ignores them otherwise, and that
+    /// no group has the name of an option
+    ///
+    /// Unlike the lints this is checked by all `ConfigBuilder::build` functions before the
+    /// arguments are parsed, so a misconfigured tool fails right away.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.name.is_empty() {
+            return Err(ConfigError::InvalidMetadata { field: "name" });
+        }
+        if self.version.is_empty() {
+            return Err(ConfigError::InvalidMetadata { field: "version" });
+        }
+
+        for group in &self.required_one_of {
+            if self.options.iter().any(|option| option.name == group.name) {
+                return Err(ConfigError::GroupNameClash { name: group.name });
+            }
+        }
+
+        let references = self
+            .sorted_options(|_| true)
+            .into_iter()
+            .flat_map(|option| option.requires.iter().map(move |to| (option.name, *to)))
+            .chain(
+                self.required_one_of
+                    .iter()
+                    .flat_map(|group| group.options.iter().map(move |to| (group.name, *to))),
+            );
+        for (from, to) in references {
+            let exists =
+                self.options.iter().any(|option| option.name == to) || RESERVED_FLAGS.contains(&to);
+            if !exists {
+                return Err(ConfigError::DanglingReference { from, to });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a list of options for common mistakes, including duplicate names
+    ///
+    /// * `options` The options as they will be used to build the `Configuration`
+    pub fn lint_options(options: &[ConfigOption]) -> Vec<ConfigLint> {
+        let mut counts: HashMap<&'static str, usize> = HashMap::new();
+        for option in options {
+            *counts.entry(option.name).or_insert(0) += 1;
+        }
+
+        let mut duplicates: Vec<(&'static str, usize)> =
+            counts.into_iter().filter(|(_, count)| *count > 1).collect();
+        duplicates.sort_unstable();
+
+        duplicates
+            .into_iter()
+            .map(|(name, count)| ConfigLint::DuplicateName { name, count })
+            .chain(options.iter().flat_map(lint_option))
+            .collect()
+    }
+}
+
+fn lint_option(option: &ConfigOption) -> Vec<ConfigLint> {
+    let mut lints = vec![];
+    if option.name.is_empty() {
+        lints.push(ConfigLint::EmptyName);
+    }
+    if option.help.is_empty() {
+        lints.push(ConfigLint::EmptyHelp { name: option.name });
+    }
+    if option.required && option.default.is_some() {
+        lints.push(ConfigLint::RequiredWithDefault { name: option.name });
+    }
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConfigLint;
+    use crate::{ConfigError, ConfigOption, Configuration, OptionGroup};
+
+    const VALID: ConfigOption = ConfigOption {
+        name: "valid",
+        takes_argument: true,
+        help: "A perfectly fine option",
+        ..ConfigOption::DEFAULT
+    };
+
+    fn configuration(options: &[ConfigOption]) -> Configuration {
+        Configuration {
+            name: "Test Tool",
+            version: "0.1",
+            about: "blabla",
+            options: options.iter().cloned().collect(),
+            ..Configuration::default()
+        }
+    }
+
+    #[test]
+    fn valid_options() {
+        assert!(Configuration::lint_options(&[VALID]).is_empty());
+        assert!(configuration(&[VALID]).lint().is_empty());
+    }
+
+    #[test]
+    fn validate_metadata() {
+        assert!(configuration(&[VALID]).validate().is_ok());
+
+        let empty_name = Configuration {
+            name: "",
+            ..configuration(&[VALID])
+        };
+        assert!(matches!(
+            empty_name.validate(),
+            Err(ConfigError::InvalidMetadata { field: "name" })
+        ));
+
+        let empty_version = Configuration {
+            version: "",
+            ..configuration(&[VALID])
+        };
+        assert!(matches!(
+            empty_version.validate(),
+            Err(ConfigError::InvalidMetadata { field: "version" })
+        ));
+    }
+
+    #[test]
+    fn validate_references() {
+        let requires = ConfigOption {
+            name: "requires",
+            requires: &["valid"],
+            ..VALID
+        };
+        assert!(configuration(&[VALID, requires.clone()]).validate().is_ok());
+
+        assert!(matches!(
+            configuration(&[requires]).validate(),
+            Err(ConfigError::DanglingReference {
+                from: "requires",
+                to: "valid"
+            })
+        ));
+
+        let grouped = Configuration {
+            required_one_of: vec![OptionGroup {
+                name: "mode",
+                options: &["valid", "invalid"],
+            }],
+            ..configuration(&[VALID])
+        };
+        assert!(matches!(
+            grouped.validate(),
+            Err(ConfigError::DanglingReference {
+                from: "mode",
+                to: "invalid"
+            })
+        ));
+
+        let clash = Configuration {
+            required_one_of: vec![OptionGroup {
+                name: "valid",
+                options: &["valid"],
+            }],
+            ..configuration(&[VALID])
+        };
+        assert!(matches!(
+            clash.validate(),
+            Err(ConfigError::GroupNameClash { name: "valid" })
+        ));
+    }
+
+    #[test]
+    fn duplicate_name() {
+        let duplicate = ConfigOption {
+            help: "Same name, different help",
+            ..VALID
+        };
+        assert_eq!(
+            Configuration::lint_options(&[VALID, duplicate, VALID]),
+            vec![ConfigLint::DuplicateName {
+                name: "valid",
+                count: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn empty_name() {
+        let option = ConfigOption { name: "", ..VALID };
+        let lints = configuration(&[option]).lint();
+        assert_eq!(lints, vec![ConfigLint::EmptyName]);
+        assert!(lints[0].is_error());
+    }
+
+    #[test]
+    fn empty_help() {
+        let option = ConfigOption { help: "", ..VALID };
+        let lints = configuration(&[option]).lint();
+        assert_eq!(lints, vec![ConfigLint::EmptyHelp { name: "valid" }]);
+        assert!(!lints[0].is_error());
+    }
+
+    #[test]
+    fn required_with_default() {
+        let option = ConfigOption {
+            required: true,
+            default: Some("foo"),
+            ..VALID
+        };
+        assert_eq!(
+            configuration(&[option]).lint(),
+            vec![ConfigLint::RequiredWithDefault { name: "valid" }]
+        );
+    }
+}
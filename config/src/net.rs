@@ -0,0 +1,129 @@
+//! This module contains the validation and parsing of values that are network addresses.
+
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use crate::{ConfigError, ConfigOption, ResolvedValues};
+
+/// What kind of network address the value of an option has to be, checked while the
+/// arguments are parsed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AddressKind {
+    /// An IPv4 or IPv6 address without a port, e.g. `127.0.0.1` or `::1`
+    Ip,
+    /// An IP address with a port, e.g. `0.0.0.0:8080` or `[::1]:8080`
+    Socket,
+}
+
+impl AddressKind {
+    /// Checks whether the given value is an address of this kind, the error is the message
+    /// that clap shows to the user
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        let valid = match self {
+            AddressKind::Ip => IpAddr::from_str(value).is_ok(),
+            AddressKind::Socket => SocketAddr::from_str(value).is_ok(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("'{}' is not {}", value, self))
+        }
+    }
+}
+
+impl Display for AddressKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            AddressKind::Ip => "an IP address (e.g. 127.0.0.1)",
+            AddressKind::Socket => "a socket address (e.g. 127.0.0.1:8080)",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+impl ResolvedValues {
+    /// Parses the value of an option as an IP address
+    ///
+    /// Returns `None` if the option has no value, for `list` options this is the first value.
+    pub fn get_ip_addr(&self, option: &ConfigOption) -> Result<Option<IpAddr>, ConfigError> {
+        self.get_address(option)
+    }
+
+    /// Parses the value of an option as a socket address, i.e. an IP address and a port
+    ///
+    /// Returns `None` if the option has no value, for `list` options this is the first value.
+    pub fn get_socket_addr(
+        &self,
+        option: &ConfigOption,
+    ) -> Result<Option<SocketAddr>, ConfigError> {
+        self.get_address(option)
+    }
+
+    fn get_address<A: FromStr>(&self, option: &ConfigOption) -> Result<Option<A>, ConfigError> {
+        let value = match self.first_value(option) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        value
+            .parse()
+            .map(Some)
+            .map_err(|_| ConfigError::InvalidAddress {
+                name: option.name.to_string(),
+                value: value.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AddressKind;
+    use crate::{ConfigError, ConfigOption, ResolvedValues};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    const ADDRESS: ConfigOption = ConfigOption {
+        name: "listen-address",
+        takes_argument: true,
+        address: Some(AddressKind::Socket),
+        ..ConfigOption::DEFAULT
+    };
+
+    #[test]
+    fn validate() {
+        assert!(AddressKind::Socket.validate("127.0.0.1:9000").is_ok());
+        assert!(AddressKind::Socket.validate("[::1]:9000").is_ok());
+        assert!(AddressKind::Socket.validate("127.0.0.1").is_err());
+        assert!(AddressKind::Socket.validate("localhost:9000").is_err());
+
+        assert!(AddressKind::Ip.validate("::1").is_ok());
+        assert!(AddressKind::Ip.validate("127.0.0.1:9000").is_err());
+        assert!(AddressKind::Ip.validate("300.0.0.1").is_err());
+    }
+
+    #[test]
+    fn get_addresses() {
+        let values = ResolvedValues::with_value(ADDRESS, "127.0.0.1:9000");
+        assert_eq!(
+            values
+                .get_socket_addr(&ADDRESS)
+                .expect("Error parsing address"),
+            Some(SocketAddr::from(([127, 0, 0, 1], 9000)))
+        );
+        assert!(matches!(
+            values.get_ip_addr(&ADDRESS),
+            Err(ConfigError::InvalidAddress { name, .. }) if name == "listen-address"
+        ));
+
+        let values = ResolvedValues::with_value(ADDRESS, "10.0.0.1");
+        assert_eq!(
+            values.get_ip_addr(&ADDRESS).expect("Error parsing address"),
+            Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+        );
+        assert_eq!(
+            ResolvedValues::default()
+                .get_socket_addr(&ADDRESS)
+                .expect("Error parsing address"),
+            None
+        );
+    }
+}
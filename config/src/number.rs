@@ -0,0 +1,129 @@
+//! This module contains the parsing of numeric values, which may contain separators to make
+//! large numbers easier to read.
+
+use std::str::FromStr;
+
+use crate::{ConfigError, ConfigOption, ResolvedValues};
+
+impl ResolvedValues {
+    /// Parses the value of an option as a number
+    ///
+    /// The value may contain separators between digits, either Rust style underscores
+    /// (`1_000_000`) or commas between groups of three digits (`1,000,000`). Both styles can't
+    /// be mixed and separators can't be doubled or appear at the start or end of the number.
+    ///
+    /// Returns `None` if the option has no value, for `list` options this is the first value.
+    pub fn get_number<N: FromStr>(&self, option: &ConfigOption) -> Result<Option<N>, ConfigError> {
+        let value = match self.first_value(option) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        strip_separators(value)
+            .and_then(|number| number.parse().ok())
+            .map(Some)
+            .ok_or_else(|| ConfigError::InvalidNumber {
+                name: option.name.to_string(),
+                value: value.to_string(),
+            })
+    }
+}
+
+// Removes the separators from a number, returns None if they are not placed properly
+fn strip_separators(value: &str) -> Option<String> {
+    let unsigned = value.trim_start_matches(['+', '-']);
+    if value.len() - unsigned.len() > 1 {
+        return None;
+    }
+    let sign = &value[..value.len() - unsigned.len()];
+
+    match (unsigned.contains('_'), unsigned.contains(',')) {
+        (false, false) => Some(value.to_string()),
+        (true, true) => None,
+        (true, false) => {
+            let bytes = unsigned.as_bytes();
+            let separated_digits = bytes.iter().enumerate().all(|(i, &byte)| {
+                byte != b'_'
+                    || (i > 0
+                        && bytes[i - 1].is_ascii_digit()
+                        && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+            });
+            if separated_digits {
+                Some(format!("{}{}", sign, unsigned.replace('_', "")))
+            } else {
+                None
+            }
+        }
+        (false, true) => {
+            // Only the integer part can be grouped with commas
+            let (integer, fraction) = match unsigned.find('.') {
+                Some(index) => unsigned.split_at(index),
+                None => (unsigned, ""),
+            };
+            let groups: Vec<&str> = integer.split(',').collect();
+            let first_valid = (1..=3).contains(&groups[0].len());
+            let rest_valid = groups[1..].iter().all(|group| group.len() == 3);
+            let all_digits = groups
+                .iter()
+                .all(|group| group.bytes().all(|byte| byte.is_ascii_digit()));
+            if first_valid && rest_valid && all_digits {
+                Some(format!("{}{}{}", sign, groups.concat(), fraction))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_separators;
+    use crate::{ConfigError, ConfigOption, ResolvedValues};
+
+    const MAX_BYTES: ConfigOption = ConfigOption {
+        name: "max-bytes",
+        takes_argument: true,
+        ..ConfigOption::DEFAULT
+    };
+
+    #[test]
+    fn separators() {
+        assert_eq!(strip_separators("1000000"), Some(String::from("1000000")));
+        assert_eq!(strip_separators("1_000_000"), Some(String::from("1000000")));
+        assert_eq!(strip_separators("1,000,000"), Some(String::from("1000000")));
+        assert_eq!(strip_separators("-10_0"), Some(String::from("-100")));
+        assert_eq!(strip_separators("1,000.5"), Some(String::from("1000.5")));
+
+        assert_eq!(strip_separators("1__000"), None);
+        assert_eq!(strip_separators("_1000"), None);
+        assert_eq!(strip_separators("1000_"), None);
+        assert_eq!(strip_separators("1,00"), None);
+        assert_eq!(strip_separators("1000,000"), None);
+        assert_eq!(strip_separators("1_000,000"), None);
+        assert_eq!(strip_separators("--1"), None);
+    }
+
+    #[test]
+    fn get_number() {
+        assert_eq!(
+            ResolvedValues::with_value(MAX_BYTES, "1_000_000")
+                .get_number(&MAX_BYTES)
+                .ok(),
+            Some(Some(1_000_000u64))
+        );
+        assert_eq!(
+            ResolvedValues::with_value(MAX_BYTES, "1000000")
+                .get_number(&MAX_BYTES)
+                .ok(),
+            Some(Some(1_000_000u64))
+        );
+        assert_eq!(
+            ResolvedValues::default().get_number::<u64>(&MAX_BYTES).ok(),
+            Some(None)
+        );
+
+        assert!(matches!(
+            ResolvedValues::with_value(MAX_BYTES, "1__000").get_number::<u64>(&MAX_BYTES),
+            Err(ConfigError::InvalidNumber { name, value }) if name == "max-bytes" && value == "1__000"
+        ));
+    }
+}
@@ -0,0 +1,122 @@
+//! This module contains the checks that can be attached to options whose values are
+//! filesystem paths.
+
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// What a path given as the value of an option is expected to point to, checked right after
+/// the values have been resolved
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PathKind {
+    /// The path has to be an existing file
+    ExistingFile,
+    /// The path has to be an existing directory
+    ExistingDir,
+    /// The path has to be an existing directory that we are allowed to write to
+    WritableDir,
+    /// The directory containing the path has to exist, the path itself does not need to
+    /// (e.g. for files that will be created)
+    ParentExists,
+}
+
+impl PathKind {
+    /// Checks whether the given path matches this expectation
+    pub fn matches(&self, path: &Path) -> bool {
+        match self {
+            PathKind::ExistingFile => path.is_file(),
+            PathKind::ExistingDir => path.is_dir(),
+            PathKind::WritableDir => path.is_dir() && is_writable(path),
+            PathKind::ParentExists => match path.parent() {
+                // A relative path with a single component lives in the working directory
+                Some(parent) if parent.as_os_str().is_empty() => true,
+                Some(parent) => parent.is_dir(),
+                // This is the root directory
+                None => true,
+            },
+        }
+    }
+}
+
+impl Display for PathKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            PathKind::ExistingFile => "an existing file",
+            PathKind::ExistingDir => "an existing directory",
+            PathKind::WritableDir => "a writable directory",
+            PathKind::ParentExists => "a path in an existing directory",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+#[cfg(unix)]
+fn is_writable(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    match CString::new(path.as_os_str().as_bytes()) {
+        // SAFETY: the pointer is valid and NUL terminated for the duration of the call
+        Ok(path) => unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 },
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_writable(path: &Path) -> bool {
+    path.metadata()
+        .map(|metadata| !metadata.permissions().readonly())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathKind;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Creates a fresh directory containing a file named "file"
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("stackable-config-path-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("Error creating test directory");
+        fs::write(dir.join("file"), "").expect("Error creating test file");
+        dir
+    }
+
+    #[test]
+    fn existing_file() {
+        let dir = test_dir("existing-file");
+        assert!(PathKind::ExistingFile.matches(&dir.join("file")));
+        assert!(!PathKind::ExistingFile.matches(&dir));
+        assert!(!PathKind::ExistingFile.matches(&dir.join("missing")));
+        fs::remove_dir_all(dir).expect("Error removing test directory");
+    }
+
+    #[test]
+    fn existing_dir() {
+        let dir = test_dir("existing-dir");
+        assert!(PathKind::ExistingDir.matches(&dir));
+        assert!(!PathKind::ExistingDir.matches(&dir.join("file")));
+        assert!(!PathKind::ExistingDir.matches(&dir.join("missing")));
+        fs::remove_dir_all(dir).expect("Error removing test directory");
+    }
+
+    #[test]
+    fn writable_dir() {
+        let dir = test_dir("writable-dir");
+        assert!(PathKind::WritableDir.matches(&dir));
+        assert!(!PathKind::WritableDir.matches(&dir.join("file")));
+        assert!(!PathKind::WritableDir.matches(&dir.join("missing")));
+        fs::remove_dir_all(dir).expect("Error removing test directory");
+    }
+
+    #[test]
+    fn parent_exists() {
+        let dir = test_dir("parent-exists");
+        assert!(PathKind::ParentExists.matches(&dir.join("missing")));
+        assert!(PathKind::ParentExists.matches(&PathBuf::from("relative")));
+        assert!(!PathKind::ParentExists.matches(&dir.join("missing").join("missing")));
+        fs::remove_dir_all(dir).expect("Error removing test directory");
+    }
+}
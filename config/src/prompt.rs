@@ -0,0 +1,100 @@
+//! This module provides the interactive fallback that is used when a required option is missing
+//! and `Configuration::interactive` is enabled.
+//! Prompting is abstracted behind the `Prompt` trait so that it can be replaced with a fake
+//! implementation in tests.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use crate::ConfigOption;
+
+/// A source of values for required options that were not provided
+pub(crate) trait Prompt {
+    /// Whether it is possible to ask the user at all, for a real terminal this means
+    /// that stdin is connected to a TTY
+    fn is_interactive(&self) -> bool;
+
+    /// Ask the user for the value of the given option
+    /// Implementations must not echo the input back for options that are marked as `secret`
+    fn read_value(&mut self, option: &ConfigOption) -> io::Result<String>;
+}
+
+/// Prompts on stderr and reads the answer from stdin
+pub(crate) struct TerminalPrompt;
+
+impl Prompt for TerminalPrompt {
+    fn is_interactive(&self) -> bool {
+        io::stdin().is_terminal()
+    }
+
+    fn read_value(&mut self, option: &ConfigOption) -> io::Result<String> {
+        let mut stderr = io::stderr();
+        write!(stderr, "{} ({}): ", option.name, option.help)?;
+        stderr.flush()?;
+
+        let value = if option.secret {
+            read_line_without_echo()?
+        } else {
+            read_line()?
+        };
+        Ok(value)
+    }
+}
+
+fn read_line() -> io::Result<String> {
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+}
+
+// Temporarily disables echoing on the terminal connected to stdin while reading the value
+// The previous terminal settings are restored even if reading fails
+#[cfg(unix)]
+fn read_line_without_echo() -> io::Result<String> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = io::stdin().as_raw_fd();
+    let mut original = std::mem::MaybeUninit::<libc::termios>::uninit();
+    // SAFETY: tcgetattr fully initializes the struct if it returns 0
+    if unsafe { libc::tcgetattr(fd, original.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let original = unsafe { original.assume_init() };
+
+    let mut silent = original;
+    silent.c_lflag &= !libc::ECHO;
+    silent.c_lflag |= libc::ECHONL;
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &silent) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let result = read_line();
+
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    result
+}
+
+// We don't know how to switch off echoing here, so we refuse to read secrets rather than
+// showing them on screen
+#[cfg(not(unix))]
+fn read_line_without_echo() -> io::Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reading secrets without echo is not supported on this platform",
+    ))
+}
+
+/// Never prompts, used where asking the user is not an option (e.g. when reloading)
+pub(crate) struct NonInteractive;
+
+impl Prompt for NonInteractive {
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    fn read_value(&mut self, option: &ConfigOption) -> io::Result<String> {
+        Err(io::Error::other(format!(
+            "can't prompt for '{}'",
+            option.name
+        )))
+    }
+}
@@ -0,0 +1,121 @@
+//! This module allows building a configuration again while the program is running, so
+//! that changes to the config file can be picked up without a restart (e.g. on SIGHUP).
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+use crate::prompt::NonInteractive;
+use crate::{ConfigBuilder, ConfigError, ConfigOption, Configurable, ResolvedValues};
+
+/// Remembers everything needed to build a configuration again, this is returned by
+/// `ConfigBuilder::build_reloadable`
+///
+/// The command line arguments (and any values the user was prompted for) are fixed for the
+/// lifetime of the process, so only values coming from the config file can change.
+#[derive(Clone, Debug)]
+pub struct ConfigReloader {
+    commandline: Vec<OsString>,
+    config_file_env: String,
+    values: ResolvedValues,
+}
+
+impl ConfigReloader {
+    pub(crate) fn new(
+        mut commandline: Vec<OsString>,
+        prompted: Vec<OsString>,
+        config_file_env: &str,
+        values: ResolvedValues,
+    ) -> Self {
+        // Prompted values are options, so they go before a `--` that starts the trailing
+        // arguments, the first element is the name of the binary like in `resolve`
+        let end = commandline
+            .iter()
+            .skip(1)
+            .position(|argument| argument == "--")
+            .map_or(commandline.len(), |position| position + 1);
+        commandline.splice(end..end, prompted);
+        ConfigReloader {
+            commandline,
+            config_file_env: config_file_env.to_string(),
+            values,
+        }
+    }
+
+    /// Reads the config file again and builds a fresh config object
+    ///
+    /// Returns the new config object together with all options whose value changed since
+    /// the last build, the user is never prompted for missing values here
+    pub fn reload<T: Configurable>(&mut self) -> Result<(T, Vec<ConfigOption>), ConfigError> {
+        let description = T::get_config_description();
+        let resolved = ConfigBuilder::resolve(
+            &description,
+            self.commandline.clone(),
+            &self.config_file_env,
+            &mut NonInteractive,
+            false,
+        )?;
+
+        let changed = changed_options(&self.values, &resolved.values);
+        self.values = resolved.values.clone();
+        Ok((T::parse_resolved(resolved.values)?, changed))
+    }
+}
+
+/// Compares two sets of resolved values and returns all options whose values differ,
+/// sorted by name
+///
+/// Options that only exist in one of the two sets count as changed.
+pub fn changed_options(
+    old: &HashMap<ConfigOption, Option<Vec<String>>>,
+    new: &HashMap<ConfigOption, Option<Vec<String>>>,
+) -> Vec<ConfigOption> {
+    let mut changed: Vec<ConfigOption> = old
+        .keys()
+        .chain(new.keys().filter(|option| !old.contains_key(option)))
+        .filter(|option| old.get(option) != new.get(option))
+        .cloned()
+        .collect();
+    changed.sort();
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::changed_options;
+    use crate::ConfigOption;
+    use std::collections::HashMap;
+
+    #[test]
+    fn changed() {
+        let unchanged = ConfigOption {
+            name: "unchanged",
+            ..ConfigOption::DEFAULT
+        };
+        let modified = ConfigOption {
+            name: "modified",
+            ..ConfigOption::DEFAULT
+        };
+        let added = ConfigOption {
+            name: "added",
+            ..ConfigOption::DEFAULT
+        };
+
+        let old: HashMap<ConfigOption, Option<Vec<String>>> = [
+            (unchanged.clone(), Some(vec![String::from("a")])),
+            (modified.clone(), None),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let new: HashMap<ConfigOption, Option<Vec<String>>> = [
+            (unchanged, Some(vec![String::from("a")])),
+            (modified.clone(), Some(vec![String::from("b")])),
+            (added.clone(), None),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        assert_eq!(changed_options(&old, &new), vec![added, modified]);
+    }
+}
@@ -10,66 +10,159 @@
 //! argument corresponds precisely to one shell argument.
 
 use std::env;
-use std::error;
-use std::error::Error;
 use std::ffi::OsString;
-use std::fs::File;
+use std::fmt::{Display, Formatter};
+use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
 use bstr::{io::BufReadExt, ByteSlice};
 use log::{error, trace};
 
-type Result = ::std::result::Result<(Vec<OsString>, Vec<Box<dyn Error>>), Box<dyn error::Error>>;
+use crate::{ConfigError, ConfigFileEncoding, ConfigWarning};
+
+type Result = ::std::result::Result<(Vec<OsString>, Vec<LineError>), io::Error>;
+
+// The arguments from a config file, its path if it was read and a warning per skipped line
+type ConfigFileArgs = (Vec<OsString>, Option<PathBuf>, Vec<ConfigWarning>);
+
+/// A problem with a single line of a config file, the line is skipped
+#[derive(Debug)]
+struct LineError {
+    line: usize,
+    message: String,
+}
+
+impl Display for LineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.line, self.message)
+    }
+}
 
 /// Return a sequence of arguments derived from ripgrep rc configuration files.
 ///
 /// * `environment` - The name of an environment variable to check for an additional
-/// config file
-pub fn args(environment: &str) -> Vec<OsString> {
+///   config file
+/// * `encoding` - The encoding of the config file
+/// * `strict` - Whether a config file that can't be read is an error
+///
+/// The path of the config file is returned alongside the arguments if it could be read, as
+/// well as a warning for every line that was skipped. A file that can't be read is logged
+/// and ignored unless `strict` is set, content that is not valid in the encoding is an error.
+pub fn args(
+    environment: &str,
+    encoding: ConfigFileEncoding,
+    strict: bool,
+) -> std::result::Result<ConfigFileArgs, ConfigError> {
     let config_path = match env::var_os(environment) {
-        None => return vec![],
+        None => return Ok((vec![], None, vec![])),
         Some(config_path) => {
             if config_path.is_empty() {
-                return vec![];
+                return Ok((vec![], None, vec![]));
             }
             PathBuf::from(config_path)
         }
     };
-    let (args, errs) = match parse(&config_path) {
-        Ok((args, errs)) => (args, errs),
-        Err(err) => {
-            error!("{}", err);
-            return vec![];
-        }
-    };
-    if !errs.is_empty() {
-        for err in errs {
-            error!("{}:{}", config_path.display(), err);
+    match args_from_file(&config_path, encoding) {
+        Ok((args, warnings)) => Ok((args, Some(config_path), warnings)),
+        Err(err @ ConfigError::ConfigFileNotFound { .. }) if !strict => {
+            error!("{:#}", err);
+            Ok((vec![], None, vec![]))
         }
+        Err(err) => Err(err),
+    }
+}
+
+/// Return a sequence of arguments derived from the given config file and a warning for every
+/// line that was skipped.
+///
+/// * `config_path` - The path of the config file
+/// * `encoding` - The encoding of the config file
+///
+/// If the file can't be read `ConfigError::ConfigFileNotFound` is returned, if it is not
+/// valid in the given encoding `ConfigError::InvalidEncoding`.
+pub fn args_from_file(
+    config_path: &Path,
+    encoding: ConfigFileEncoding,
+) -> std::result::Result<(Vec<OsString>, Vec<ConfigWarning>), ConfigError> {
+    let (args, errs) = parse(config_path, encoding)?;
+    for err in &errs {
+        error!("{}:{}", config_path.display(), err);
     }
     trace!(
         "{}: arguments loaded from config file: {:?}",
         config_path.display(),
         args
     );
+    let warnings = errs
+        .into_iter()
+        .map(|err| ConfigWarning {
+            file: config_path.to_path_buf(),
+            line: err.line,
+            message: err.message,
+        })
+        .collect();
+    Ok((args, warnings))
+}
+
+/// Return a sequence of arguments derived from config file content that is already in memory,
+/// e.g. defaults compiled into the binary.
+pub fn args_from_str(content: &str) -> Vec<OsString> {
+    let (args, errs) = match parse_reader(content.as_bytes()) {
+        Ok((args, errs)) => (args, errs),
+        Err(err) => {
+            error!("defaults: {}", err);
+            return vec![];
+        }
+    };
+    for err in errs {
+        error!("defaults:{}", err);
+    }
     args
 }
 
+/// Parses config content in the format of the config file from a reader into arguments,
+/// in the order in which they have to be prepended to the command line
+///
+/// Unlike for config files, where lines that can't be parsed are logged and skipped, every
+/// problem with the content is an error here.
+pub fn parse_config_reader<R: io::Read>(
+    reader: R,
+) -> std::result::Result<Vec<OsString>, ConfigError> {
+    let invalid = |message: String| ConfigError::InvalidConfigContent { message };
+    let (args, errs) = parse_reader(reader).map_err(|err| invalid(err.to_string()))?;
+    match errs.first() {
+        Some(err) => Err(invalid(err.to_string())),
+        None => Ok(args),
+    }
+}
+
 /// Parse a single ripgrep rc file from the given path.
 ///
 /// On success, this returns a set of shell arguments, in order, that should
 /// be pre-pended to the arguments given to ripgrep at the command line.
 ///
-/// If the file could not be read, then an error is returned. If there was
-/// a problem parsing one or more lines in the file, then errors are returned
+/// If the file could not be read or decoded, then an error is returned. If there
+/// was a problem parsing one or more lines in the file, then errors are returned
 /// for each line in addition to successfully parsed arguments.
-fn parse<P: AsRef<Path>>(path: P) -> Result {
+fn parse<P: AsRef<Path>>(
+    path: P,
+    encoding: ConfigFileEncoding,
+) -> std::result::Result<(Vec<OsString>, Vec<LineError>), ConfigError> {
     let path = path.as_ref();
-    match File::open(&path) {
-        Ok(file) => parse_reader(file),
-        Err(err) => Err(From::from(format!("{}: {}", path.display(), err))),
-    }
+    let not_found = |source| ConfigError::ConfigFileNotFound {
+        path: path.to_path_buf(),
+        source,
+    };
+    let content = fs::read(path).map_err(not_found)?;
+    let content = encoding
+        .decode(content)
+        .map_err(|offset| ConfigError::InvalidEncoding {
+            path: path.to_path_buf(),
+            encoding,
+            offset,
+        })?;
+    parse_reader(&content[..]).map_err(not_found)
 }
 
 /// Parse a single ripgrep rc file from the given reader.
@@ -83,30 +176,112 @@ fn parse<P: AsRef<Path>>(path: P) -> Result {
 /// If the reader could not be read, then an error is returned. If there was a
 /// problem parsing one or more lines, then errors are returned for each line
 /// in addition to successfully parsed arguments.
+///
+/// Values that span multiple lines can be written as a heredoc, a line like
+/// `--cert <<END` starts it and a line containing only `END` ends it. The lines
+/// in between are taken as they are (including leading whitespace, comments and
+/// empty lines) and joined with `\n` into the single argument `--cert=<lines>`.
 fn parse_reader<R: io::Read>(rdr: R) -> Result {
     let bufrdr = io::BufReader::new(rdr);
     let (mut args, mut errs) = (vec![], vec![]);
     let mut line_number = 0;
+    let mut heredoc: Option<Heredoc> = None;
     bufrdr.for_byte_line_with_terminator(|line| {
         line_number += 1;
 
+        if let Some(mut doc) = heredoc.take() {
+            let content = line.strip_suffix(b"\n").unwrap_or(line);
+            let content = content.strip_suffix(b"\r").unwrap_or(content);
+            if content.trim() == doc.delimiter.as_slice() {
+                push_arg(&mut args, &mut errs, doc.start, &doc.arg);
+            } else {
+                if !doc.first_line {
+                    doc.arg.push(b'\n');
+                }
+                doc.arg.extend_from_slice(content);
+                doc.first_line = false;
+                heredoc = Some(doc);
+            }
+            return Ok(true);
+        }
+
         let line = line.trim();
         if line.is_empty() || line[0] == b'#' {
             return Ok(true);
         }
-        match line.to_os_str() {
-            Ok(osstr) => {
-                args.push(osstr.to_os_string());
-            }
-            Err(err) => {
-                errs.push(format!("{}: {}", line_number, err).into());
-            }
+        match Heredoc::start(line, line_number) {
+            Some(doc) => heredoc = Some(doc),
+            None => push_arg(&mut args, &mut errs, line_number, line),
         }
         Ok(true)
     })?;
+    if let Some(doc) = heredoc {
+        errs.push(LineError {
+            line: doc.start,
+            message: format!("heredoc is not terminated by '{}'", doc.delimiter.as_bstr()),
+        });
+    }
     Ok((args, errs))
 }
 
+// Adds a single argument, or an error if it can't be represented on this platform
+fn push_arg(args: &mut Vec<OsString>, errs: &mut Vec<LineError>, line_number: usize, arg: &[u8]) {
+    match arg.to_os_str() {
+        Ok(osstr) => {
+            args.push(osstr.to_os_string());
+        }
+        Err(err) => {
+            errs.push(LineError {
+                line: line_number,
+                message: err.to_string(),
+            });
+        }
+    }
+}
+
+// A multiline value that is being collected
+struct Heredoc {
+    // The argument so far, `--option=` followed by the lines collected until now
+    arg: Vec<u8>,
+    delimiter: Vec<u8>,
+    first_line: bool,
+    // The line number of the line that started the heredoc
+    start: usize,
+}
+
+impl Heredoc {
+    // Checks whether the (trimmed) line starts a heredoc, i.e. has the form `--option <<END`
+    // Only a bare option name can start one, so values like `--greeting=see you <<SOON` are
+    // kept as they are
+    fn start(line: &[u8], line_number: usize) -> Option<Heredoc> {
+        let position = line.rfind(" <<")?;
+        let delimiter = &line[position + 3..];
+        let option = line[..position].trim_end();
+        let valid_option = option.starts_with(b"-")
+            && !option
+                .iter()
+                .any(|byte| *byte == b'=' || byte.is_ascii_whitespace());
+        let valid_delimiter = !delimiter.is_empty()
+            && delimiter
+                .iter()
+                .all(|byte| byte.is_ascii_alphanumeric() || *byte == b'_');
+        if !valid_option || !valid_delimiter {
+            return None;
+        }
+
+        // The value is attached with `=`, otherwise a value starting with `-` (like a PEM
+        // block) would be taken for another option
+        let mut arg = option.to_vec();
+        arg.push(b'=');
+        Some(Heredoc {
+            arg,
+            delimiter: delimiter.to_vec(),
+            first_line: true,
+            start: line_number,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::parse_reader;
@@ -132,6 +307,64 @@ mod tests {
         assert_eq!(args, vec!["--context=0", "--smart-case", "-u", "--foo",]);
     }
 
+    #[test]
+    fn heredoc() {
+        let (args, errs) = parse_reader(
+            &b"\
+--before
+--cert <<END
+-----BEGIN CERTIFICATE-----
+  MIIBszCCAVmgAwIBAgIU
+# not a comment
+
+-----END CERTIFICATE-----
+END
+--after
+"[..],
+        )
+        .unwrap();
+        assert!(errs.is_empty());
+        let args: Vec<String> = args.into_iter().map(|s| s.into_string().unwrap()).collect();
+        assert_eq!(
+            args,
+            vec![
+                "--before",
+                "--cert=-----BEGIN CERTIFICATE-----\n  MIIBszCCAVmgAwIBAgIU\n# not a comment\n\n-----END CERTIFICATE-----",
+                "--after",
+            ]
+        );
+
+        let (args, errs) = parse_reader(&b"--cert <<END\nfoo\n"[..]).unwrap();
+        assert!(args.is_empty());
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_string(), "1: heredoc is not terminated by 'END'");
+    }
+
+    // Only `--option <<END` starts a heredoc, other lines ending in `<<WORD` are single
+    // arguments like before
+    #[test]
+    fn heredoc_like_values() {
+        let (args, errs) = parse_reader(
+            &b"\
+--opt=x <<EOF
+--greeting=see you <<SOON
+a <<B
+--opt x <<EOF
+"[..],
+        )
+        .unwrap();
+        assert!(errs.is_empty());
+        assert_eq!(
+            args,
+            vec![
+                OsString::from("--opt=x <<EOF"),
+                OsString::from("--greeting=see you <<SOON"),
+                OsString::from("a <<B"),
+                OsString::from("--opt x <<EOF"),
+            ]
+        );
+    }
+
     // We test that we can handle invalid UTF-8 on Unix-like systems.
     #[test]
     #[cfg(unix)]
@@ -0,0 +1,237 @@
+//! This module contains the validation and parsing of values that are URLs.
+
+use std::fmt::{Display, Formatter};
+use std::net::Ipv6Addr;
+
+use crate::{ConfigError, ConfigOption, ResolvedValues};
+
+/// A URL with an authority, e.g. `https://user@example.com:8443/api?verbose`
+///
+/// Only the parts are split, nothing is decoded or normalized apart from the scheme, which
+/// is compared case-insensitively and always returned in lowercase.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Url {
+    value: String,
+    scheme: String,
+    host: String,
+    port: Option<u16>,
+    path: String,
+    query: Option<String>,
+}
+
+impl Url {
+    /// Parses a URL of the form `scheme://[user@]host[:port][/path][?query][#fragment]`, the
+    /// error describes what is wrong with the value
+    pub fn parse(value: &str) -> Result<Url, String> {
+        if value.contains(char::is_whitespace) {
+            return Err(String::from("it contains whitespace"));
+        }
+        let (scheme, rest) = value
+            .split_once("://")
+            .ok_or_else(|| String::from("'://' is missing after the scheme"))?;
+        let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+        if !valid_scheme {
+            return Err(format!("'{}' is not a valid scheme", scheme));
+        }
+
+        let end = rest.find(&['/', '?', '#'][..]).unwrap_or(rest.len());
+        let (authority, rest) = rest.split_at(end);
+        let host_port = authority.rsplit('@').next().unwrap_or(authority);
+        let (host, port) = split_port(host_port)?;
+        if host.is_empty() {
+            return Err(String::from("the host is missing"));
+        }
+
+        let rest = rest.split('#').next().unwrap_or(rest);
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query.to_string())),
+            None => (rest, None),
+        };
+        Ok(Url {
+            value: value.to_string(),
+            scheme: scheme.to_ascii_lowercase(),
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+            query,
+        })
+    }
+
+    /// The scheme in lowercase, e.g. `https`
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// The host name or IP address, IPv6 addresses are enclosed in brackets
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The port if one is given explicitly, there is no default per scheme
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// The path, empty if the URL has none
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The query without the leading `?`
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// The URL as it was specified
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+}
+
+impl Display for Url {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+// Splits `host:port` and `[ipv6]:port`, the port is optional
+fn split_port(host_port: &str) -> Result<(&str, Option<u16>), String> {
+    let (host, port) = if host_port.starts_with('[') {
+        let close = host_port
+            .find(']')
+            .ok_or_else(|| format!("'{}' is not a valid host", host_port))?;
+        let (host, port) = host_port.split_at(close + 1);
+        if host[1..close].parse::<Ipv6Addr>().is_err() {
+            return Err(format!("'{}' is not a valid IPv6 address", host));
+        }
+        match port {
+            "" => (host, None),
+            port => match port.strip_prefix(':') {
+                Some(port) => (host, Some(port)),
+                None => return Err(format!("'{}' is not a valid host", host_port)),
+            },
+        }
+    } else {
+        match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        }
+    };
+    let port = match port {
+        Some(port) => Some(
+            port.parse()
+                .map_err(|_| format!("'{}' is not a valid port", port))?,
+        ),
+        None => None,
+    };
+    Ok((host, port))
+}
+
+// Checks whether the value is a URL with one of the allowed schemes (any scheme if there are
+// none), used as clap validator for options with `url_schemes`
+pub(crate) fn validate(value: &str, allowed_schemes: &[&str]) -> Result<Url, String> {
+    let url =
+        Url::parse(value).map_err(|err| format!("'{}' is not a valid URL: {}", value, err))?;
+    if !allowed_schemes.is_empty()
+        && !allowed_schemes
+            .iter()
+            .any(|scheme| scheme.eq_ignore_ascii_case(url.scheme()))
+    {
+        return Err(format!(
+            "the scheme of '{}' is not allowed, expected one of: {}",
+            value,
+            allowed_schemes.join(", ")
+        ));
+    }
+    Ok(url)
+}
+
+impl ResolvedValues {
+    /// Parses the value of an option as a URL whose scheme is one of `allowed_schemes`, all
+    /// schemes are allowed if it is empty
+    ///
+    /// Returns `None` if the option has no value, for `list` options this is the first value.
+    pub fn get_url(
+        &self,
+        option: &ConfigOption,
+        allowed_schemes: &[&str],
+    ) -> Result<Option<Url>, ConfigError> {
+        let value = match self.first_value(option) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        validate(value, allowed_schemes)
+            .map(Some)
+            .map_err(|message| ConfigError::InvalidUrl {
+                name: option.name.to_string(),
+                message,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Url;
+    use crate::{ConfigError, ConfigOption, ResolvedValues};
+
+    const ENDPOINT: ConfigOption = ConfigOption {
+        name: "endpoint",
+        takes_argument: true,
+        url_schemes: &["https"],
+        ..ConfigOption::DEFAULT
+    };
+
+    #[test]
+    fn parse() {
+        let url = Url::parse("HTTPS://user@example.com:8443/api/v1?verbose#top")
+            .expect("Error parsing URL");
+        assert_eq!(url.scheme(), "https");
+        assert_eq!(url.host(), "example.com");
+        assert_eq!(url.port(), Some(8443));
+        assert_eq!(url.path(), "/api/v1");
+        assert_eq!(url.query(), Some("verbose"));
+
+        let url = Url::parse("http://[::1]").expect("Error parsing URL");
+        assert_eq!(url.host(), "[::1]");
+        assert_eq!(url.port(), None);
+        assert_eq!(url.path(), "");
+
+        assert!(Url::parse("example.com/api").is_err());
+        assert!(Url::parse("1http://example.com").is_err());
+        assert!(Url::parse("https:///api").is_err());
+        assert!(Url::parse("https://example.com:port").is_err());
+        assert!(Url::parse("https://[::x]:80").is_err());
+        assert!(Url::parse("https://exa mple.com").is_err());
+    }
+
+    #[test]
+    fn get_url() {
+        let url = ResolvedValues::with_value(ENDPOINT, "https://example.com/api")
+            .get_url(&ENDPOINT, ENDPOINT.url_schemes)
+            .expect("Error parsing URL")
+            .expect("No URL");
+        assert_eq!(url.as_str(), "https://example.com/api");
+
+        assert!(matches!(
+            ResolvedValues::with_value(ENDPOINT, "http://example.com/api").get_url(&ENDPOINT, &["https"]),
+            Err(ConfigError::InvalidUrl { name, message })
+                if name == "endpoint" && message.contains("expected one of: https")
+        ));
+        assert!(matches!(
+            ResolvedValues::with_value(ENDPOINT, "https//example.com").get_url(&ENDPOINT, &["https"]),
+            Err(ConfigError::InvalidUrl { message, .. }) if message.contains("not a valid URL")
+        ));
+        assert!(ResolvedValues::with_value(ENDPOINT, "http://example.com")
+            .get_url(&ENDPOINT, &[])
+            .is_ok());
+        assert_eq!(
+            ResolvedValues::default()
+                .get_url(&ENDPOINT, &["https"])
+                .expect("Error parsing URL"),
+            None
+        );
+    }
+}
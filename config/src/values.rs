@@ -0,0 +1,406 @@
+//! This module contains the container for the values that were resolved for all options,
+//! which is handed to `Configurable::parse_resolved`.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::doc::MASK;
+use crate::{ConfigError, ConfigOption, ConfigWarning};
+
+/// The values that were resolved for all options of a configuration
+///
+/// This dereferences to a `HashMap<ConfigOption, Option<Vec<String>>>` whose keys are all
+/// ConfigOptions that were returned in the `get_config_description()` call.
+///
+/// The value in the HashMap can have three meanings:
+/// - None: this parameter was not specified on the command line
+/// - Some(Vec<String>) with an empty Vector: this is a boolean parameter
+///   and it was present on the command line
+/// - Some(Vec<String>) with one or more list elements: parameter that takes
+///   a value and one or more values were specified
+///
+/// Values of options that are marked as `path` (or have a `path_kind`) may not be valid UTF-8,
+/// the strings in the HashMap are a lossy conversion for those, use `get_os_path` to get the
+/// actual value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResolvedValues {
+    values: HashMap<ConfigOption, Option<Vec<String>>>,
+    // Raw values of all path options that have a value
+    os_values: HashMap<ConfigOption, Vec<OsString>>,
+    // The config file the values were read from, if any
+    pub(crate) config_file: Option<PathBuf>,
+    // Everything after `--` if `Configuration::trailing_args` is set
+    pub(crate) trailing: Vec<OsString>,
+    // Lines of the config files that were skipped
+    pub(crate) warnings: Vec<ConfigWarning>,
+    // The defaults of all options that take an argument, with the placeholders replaced
+    pub(crate) defaults: HashMap<ConfigOption, String>,
+}
+
+impl ResolvedValues {
+    // Stores the values of an option, these have to be valid UTF-8 unless it is a path option
+    pub(crate) fn insert(
+        &mut self,
+        option: ConfigOption,
+        values: Option<Vec<OsString>>,
+    ) -> Result<(), ConfigError> {
+        let values = match values {
+            None => None,
+            Some(values) if option.is_path() => {
+                let strings = values
+                    .iter()
+                    .map(|value| value.to_string_lossy().into_owned())
+                    .collect();
+                self.os_values.insert(option.clone(), values);
+                Some(strings)
+            }
+            Some(values) => Some(
+                values
+                    .into_iter()
+                    .map(|value| {
+                        value.into_string().map_err(|_| ConfigError::InvalidUtf8 {
+                            name: option.name.to_string(),
+                        })
+                    })
+                    .collect::<Result<Vec<String>, ConfigError>>()?,
+            ),
+        };
+        self.values.insert(option, values);
+        Ok(())
+    }
+
+    // Values with only the given option set to a single value, for testing the typed getters
+    #[cfg(test)]
+    pub(crate) fn with_value(option: ConfigOption, value: &str) -> ResolvedValues {
+        let mut values = ResolvedValues::default();
+        values
+            .insert(option, Some(vec![OsString::from(value)]))
+            .expect("Error inserting value");
+        values
+    }
+
+    /// Returns the value of an option as a path, without going through a lossy UTF-8
+    /// conversion for options that are marked as `path`
+    ///
+    /// Returns `None` if the option has no value, for `list` options this is the first value,
+    /// use `get_os_values` to get all of them.
+    pub fn get_os_path(&self, option: &ConfigOption) -> Option<PathBuf> {
+        self.get_os_values(option)?
+            .into_iter()
+            .next()
+            .map(PathBuf::from)
+    }
+
+    /// Returns all values of an option without going through a lossy UTF-8 conversion for
+    /// options that are marked as `path`
+    pub fn get_os_values(&self, option: &ConfigOption) -> Option<Vec<OsString>> {
+        match self.os_values.get(option) {
+            Some(values) => Some(values.clone()),
+            None => self
+                .values
+                .get(option)?
+                .as_ref()
+                .map(|values| values.iter().map(OsString::from).collect()),
+        }
+    }
+
+    /// Returns the first value of an option, `None` if the option has no value or is a switch
+    ///
+    /// This is the value for options that are not `list` options, the typed getters like
+    /// `get_number` parse it.
+    pub fn first_value(&self, option: &ConfigOption) -> Option<&str> {
+        self.values
+            .get(option)?
+            .as_ref()?
+            .first()
+            .map(String::as_str)
+    }
+
+    /// Renders the values as command line arguments that resolve to the same values again
+    ///
+    /// Options are sorted by name, values that are equal to the default of the option and
+    /// options without a value are left out. Values are written as `--option=value`, repeated
+    /// for every value of a `list` option, and switches as `--option`. A `list` option whose
+    /// default was cleared is written as `--option=`. The name of the binary is not included.
+    ///
+    /// * `mask_secrets` If true, the values of `secret` options are replaced by a mask, the
+    ///   result can't be used to reproduce the configuration then
+    pub fn to_command_line(&self, mask_secrets: bool) -> Vec<OsString> {
+        let mut options: Vec<&ConfigOption> = self.values.keys().collect();
+        options.sort();
+
+        let mut arguments = vec![];
+        for option in options {
+            let default = self.defaults.get(option);
+            let values = match self.get_os_values(option) {
+                Some(values) => values,
+                // Without this the default would be used again
+                None if option.list && default.is_some() => {
+                    arguments.push(OsString::from(format!("--{}=", option.name)));
+                    continue;
+                }
+                None => continue,
+            };
+            if values.is_empty() {
+                arguments.push(OsString::from(format!("--{}", option.name)));
+                continue;
+            }
+            if let (Some(default), [value]) = (default, &values[..]) {
+                if value.as_os_str() == default.as_str() {
+                    continue;
+                }
+            }
+            for value in values {
+                let mut argument = OsString::from(format!("--{}=", option.name));
+                if option.secret && mask_secrets {
+                    argument.push(MASK);
+                } else {
+                    // Values starting with @ would be read from a file otherwise
+                    if value.to_str().is_some_and(|value| value.starts_with('@')) {
+                        argument.push("@");
+                    }
+                    argument.push(value);
+                }
+                arguments.push(argument);
+            }
+        }
+        arguments
+    }
+
+    /// Parses the values of a `list` option of the form `key=value` into a map, e.g.
+    /// `--label env=prod --label team=data`
+    ///
+    /// Every value is split on the first `=`, so the value may contain `=` itself but the key
+    /// can't. If a key is given more than once the last value wins, which means values from
+    /// the command line override those from the config file.
+    ///
+    /// Returns an empty map if the option has no value.
+    pub fn get_map(&self, option: &ConfigOption) -> Result<HashMap<String, String>, ConfigError> {
+        let values = match self.get(option) {
+            Some(Some(values)) => values,
+            _ => return Ok(HashMap::new()),
+        };
+        values
+            .iter()
+            .map(|value| match value.split_once('=') {
+                Some((key, value)) => Ok((key.to_string(), value.to_string())),
+                None => Err(ConfigError::InvalidMapEntry {
+                    name: option.name.to_string(),
+                    value: value.to_string(),
+                }),
+            })
+            .collect()
+    }
+
+    /// Parses the value of an option into an enum (or any other type implementing `FromStr`)
+    ///
+    /// This is meant to be used together with `ConfigOption::possible_values`, so clap already
+    /// rejects values that are not allowed, and the error lists them in case the two disagree.
+    ///
+    /// Returns `None` if the option has no value, for `list` options this is the first value.
+    pub fn get_enum<T: FromStr>(&self, option: &ConfigOption) -> Result<Option<T>, ConfigError> {
+        let value = match self.first_value(option) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        value
+            .parse()
+            .map(Some)
+            .map_err(|_| ConfigError::InvalidEnumValue {
+                name: option.name.to_string(),
+                value: value.to_string(),
+                possible_values: option.possible_values,
+            })
+    }
+
+    /// The arguments that followed `--` on the command line, in their original order
+    ///
+    /// This is always empty unless `Configuration::trailing_args` is set.
+    pub fn trailing_args(&self) -> &[OsString] {
+        &self.trailing
+    }
+
+    /// The config file that values were read from, `None` if no config file was specified,
+    /// it could not be read or `--no-config` was set
+    ///
+    /// This is only meant for diagnostics, e.g. to log which file the tool is running with.
+    pub fn config_file_used(&self) -> Option<&Path> {
+        self.config_file.as_deref()
+    }
+
+    /// The lines of the config file (and `Configuration::defaults_file`) that could not be
+    /// parsed and were skipped, the values from all other lines are used nonetheless
+    ///
+    /// These are logged as errors already, this is meant for tools that want to report them
+    /// on their own, e.g. as warnings at startup.
+    pub fn warnings(&self) -> &[ConfigWarning] {
+        &self.warnings
+    }
+
+    /// Returns the underlying HashMap with the values of all options
+    pub fn into_inner(self) -> HashMap<ConfigOption, Option<Vec<String>>> {
+        self.values
+    }
+}
+
+impl Deref for ResolvedValues {
+    type Target = HashMap<ConfigOption, Option<Vec<String>>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.values
+    }
+}
+
+impl From<ResolvedValues> for HashMap<ConfigOption, Option<Vec<String>>> {
+    fn from(values: ResolvedValues) -> Self {
+        values.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResolvedValues;
+    use crate::{ConfigError, ConfigOption};
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    const PATH: ConfigOption = ConfigOption {
+        name: "path",
+        takes_argument: true,
+        path: true,
+        ..ConfigOption::DEFAULT
+    };
+    const NAME: ConfigOption = ConfigOption {
+        name: "name",
+        takes_argument: true,
+        ..ConfigOption::DEFAULT
+    };
+
+    #[test]
+    fn string_values() {
+        let mut values = ResolvedValues::default();
+        values
+            .insert(NAME, Some(vec![OsString::from("foo")]))
+            .expect("Error inserting value");
+        values.insert(PATH, None).expect("Error inserting value");
+
+        assert_eq!(values.get(&NAME), Some(&Some(vec![String::from("foo")])));
+        assert_eq!(values.get_os_path(&NAME), Some(PathBuf::from("foo")));
+        assert_eq!(values.get_os_path(&PATH), None);
+        assert_eq!(values.first_value(&NAME), Some("foo"));
+        assert_eq!(values.first_value(&PATH), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn non_utf8_values() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let raw = OsString::from_vec(b"/data/foo\xFFbar".to_vec());
+        let mut values = ResolvedValues::default();
+        values
+            .insert(PATH, Some(vec![raw.clone()]))
+            .expect("Error inserting value");
+        assert_eq!(values.get_os_path(&PATH), Some(PathBuf::from(raw.clone())));
+
+        assert!(matches!(
+            values.insert(NAME, Some(vec![raw])),
+            Err(ConfigError::InvalidUtf8 { name }) if name == "name"
+        ));
+    }
+
+    #[test]
+    fn enum_values() {
+        #[derive(Debug, PartialEq)]
+        enum Mode {
+            Safe,
+            Unsafe,
+        }
+
+        impl FromStr for Mode {
+            type Err = ();
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                match value {
+                    "safe" => Ok(Mode::Safe),
+                    "unsafe" => Ok(Mode::Unsafe),
+                    _ => Err(()),
+                }
+            }
+        }
+
+        const MODE: ConfigOption = ConfigOption {
+            name: "mode",
+            takes_argument: true,
+            possible_values: &["safe", "unsafe"],
+            ..ConfigOption::DEFAULT
+        };
+
+        for (value, mode) in [("safe", Mode::Safe), ("unsafe", Mode::Unsafe)] {
+            assert_eq!(
+                ResolvedValues::with_value(MODE, value)
+                    .get_enum::<Mode>(&MODE)
+                    .expect("Error parsing enum"),
+                Some(mode)
+            );
+        }
+
+        let values = ResolvedValues::with_value(MODE, "fast");
+        let err = values.get_enum::<Mode>(&MODE).unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidEnumValue { ref value, .. } if value == "fast"));
+        assert!(err.to_string().ends_with("possible values: safe, unsafe"));
+        assert_eq!(
+            values.get_enum::<Mode>(&NAME).expect("Error parsing enum"),
+            None
+        );
+    }
+
+    #[test]
+    fn map_values() {
+        const LABEL: ConfigOption = ConfigOption {
+            name: "label",
+            takes_argument: true,
+            list: true,
+            ..ConfigOption::DEFAULT
+        };
+
+        let mut values = ResolvedValues::default();
+        values
+            .insert(
+                LABEL,
+                Some(vec![
+                    OsString::from("env=prod"),
+                    OsString::from("team=data"),
+                    OsString::from("query=a=b"),
+                    OsString::from("env=dev"),
+                ]),
+            )
+            .expect("Error inserting value");
+        let expected: HashMap<String, String> =
+            vec![("env", "dev"), ("team", "data"), ("query", "a=b")]
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+        assert_eq!(values.get_map(&LABEL).expect("Error parsing map"), expected);
+        assert_eq!(
+            values.get_map(&NAME).expect("Error parsing map"),
+            HashMap::new()
+        );
+
+        values
+            .insert(
+                LABEL,
+                Some(vec![OsString::from("env=prod"), OsString::from("prod")]),
+            )
+            .expect("Error inserting value");
+        assert!(matches!(
+            values.get_map(&LABEL),
+            Err(ConfigError::InvalidMapEntry { name, value }) if name == "label" && value == "prod"
+        ));
+    }
+}